@@ -0,0 +1,286 @@
+use crate::automaton;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::grid::ALIVE;
+
+// Objects are classified by running them for at most this many generations,
+// and only objects that fit in MAX_SIZE cells both ways are encoded, as
+// apgsearch does
+const MAX_PERIOD: usize = 64;
+const MAX_SIZE: u16 = 40;
+// Room around the object for spaceships to move while it runs
+const MARGIN: u16 = MAX_PERIOD as u16 / 2 + 2;
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+// Live cells relative to the top left corner of their bounding box, sorted by
+// row then column, with the size of the box
+#[derive(Clone, PartialEq, Debug)]
+struct Phase {
+    cells: Vec<(u16, u16)>,
+    width: u16,
+    height: u16,
+}
+
+impl Phase {
+    // The cells of the grid, and the top left corner of their bounding box
+    fn of(grid: &Grid) -> Option<(Self, (u16, u16))> {
+        let (left, top, right, bottom) = grid.bounding_box()?;
+        let mut cells = Vec::new();
+        for height in top..=bottom {
+            for width in left..=right {
+                if grid.is_alive(grid[(width, height)]) {
+                    cells.push((width - left, height - top));
+                }
+            }
+        }
+        let phase = Phase {
+            cells,
+            width: right - left + 1,
+            height: bottom - top + 1,
+        };
+        Some((phase, (left, top)))
+    }
+
+    // The extended Wechsler format of the phase in one of its 8 orientations.
+    // Rows are cut in strips of 5, separated by 'z', and each column of a strip
+    // is written as the digit of its cells, the top one in the lowest bit. Runs
+    // of empty columns are shortened: 'w' for 2, 'x' for 3, and 'y' and a digit
+    // for 4 or more, and those at the end of a strip are left out.
+    fn wechsler(&self, orientation: u8) -> String {
+        let is_transposed = orientation & 4 != 0;
+        let (width, height) = if is_transposed {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let mut cells = vec![false; width as usize * height as usize];
+        for (column, row) in &self.cells {
+            let (mut column, mut row) = (*column, *row);
+            if orientation & 1 != 0 {
+                column = self.width - 1 - column;
+            }
+            if orientation & 2 != 0 {
+                row = self.height - 1 - row;
+            }
+            if is_transposed {
+                (column, row) = (row, column);
+            }
+            cells[row as usize * width as usize + column as usize] = true;
+        }
+
+        let mut code = String::new();
+        for strip in 0..height.div_ceil(5) {
+            if strip > 0 {
+                code.push('z');
+            }
+            let mut empty_columns = 0;
+            for column in 0..width {
+                let digit = (0..5)
+                    .filter(|row| {
+                        let row = 5 * strip + row;
+                        row < height && cells[row as usize * width as usize + column as usize]
+                    })
+                    .fold(0, |digit, row| digit | 1 << row);
+                if digit == 0 {
+                    empty_columns += 1;
+                    continue;
+                }
+                match empty_columns {
+                    0 => {}
+                    1 => code.push('0'),
+                    2 => code.push('w'),
+                    3 => code.push('x'),
+                    _ => {
+                        code.push('y');
+                        code.push(DIGITS[empty_columns - 4] as char);
+                    }
+                }
+                empty_columns = 0;
+                code.push(DIGITS[digit] as char);
+            }
+        }
+        code
+    }
+}
+
+/// The apgcode of the pattern of the grid, as Catagolue names objects: xs and
+/// the population for still lifes, xp and the period for oscillators, xq and
+/// the period for spaceships, then the shortest extended Wechsler format of all
+/// its phases and orientations (xs4_33 for the block, xq4_153 for the glider).
+/// None when the pattern isn't one of those or is too large.
+/// <https://conwaylife.com/wiki/Apgcode>
+pub fn apgcode(grid: &Grid) -> Option<String> {
+    let Some((first_phase, _)) = Phase::of(grid) else {
+        return Some("xs0_0".to_string());
+    };
+    if first_phase.width > MAX_SIZE || first_phase.height > MAX_SIZE {
+        return None;
+    }
+
+    // Run a copy of the pattern with room around it
+    let automaton = automaton::parse(&grid.automaton().name()).ok()?;
+    if automaton.states() != 2 {
+        return None;
+    }
+    let mut copy = Grid::new(
+        first_phase.width + 2 * MARGIN,
+        first_phase.height + 2 * MARGIN,
+    );
+    copy.set_automaton(automaton);
+    for (column, row) in &first_phase.cells {
+        copy.set_cell((column + MARGIN, row + MARGIN), ALIVE);
+    }
+
+    let mut phases = vec![first_phase.clone()];
+    let (period, moves) = loop {
+        copy.next_generation();
+        let (phase, corner) = Phase::of(&copy)?;
+        if phase == first_phase {
+            break (phases.len(), corner != (MARGIN, MARGIN));
+        }
+        if phases.len() == MAX_PERIOD || phase.width > MAX_SIZE || phase.height > MAX_SIZE {
+            return None;
+        }
+        phases.push(phase);
+    };
+    let prefix = match (period, moves) {
+        (1, false) => format!("xs{}", first_phase.cells.len()),
+        (_, false) => format!("xp{period}"),
+        (_, true) => format!("xq{period}"),
+    };
+    let code = phases
+        .iter()
+        .flat_map(|phase| (0..8).map(|orientation| phase.wechsler(orientation)))
+        .min_by(|first, second| (first.len(), first).cmp(&(second.len(), second)))?;
+    Some(format!("{prefix}_{code}"))
+}
+
+/// The live cells of an object given by its apgcode, from the top left corner
+/// of its bounding box
+pub fn parse(apgcode: &str) -> Result<Vec<(u16, u16)>, Error> {
+    let invalid =
+        |reason: &str| Error::InvalidPattern(format!("invalid apgcode \"{apgcode}\": {reason}"));
+    let (prefix, code) = apgcode
+        .trim()
+        .split_once('_')
+        .ok_or_else(|| invalid("expected a prefix, _ and the cells"))?;
+    let number = prefix
+        .strip_prefix("xs")
+        .or_else(|| prefix.strip_prefix("xp"))
+        .or_else(|| prefix.strip_prefix("xq"));
+    if number.is_none_or(|number| number.parse::<u32>().is_err()) {
+        return Err(invalid("the prefix is xs, xp or xq and a number"));
+    }
+
+    let digit = |character: char| {
+        DIGITS
+            .iter()
+            .position(|digit| *digit as char == character)
+            .ok_or_else(|| invalid("unexpected character"))
+    };
+    let mut cells = Vec::new();
+    let (mut column, mut strip) = (0_u16, 0_u16);
+    let mut characters = code.chars();
+    while let Some(character) = characters.next() {
+        let empty_columns = match character {
+            'z' => {
+                strip = strip.checked_add(1).ok_or_else(|| invalid("too large"))?;
+                column = 0;
+                continue;
+            }
+            'w' => 2,
+            'x' => 3,
+            'y' => 4 + digit(characters.next().unwrap_or('_'))? as u16,
+            _ => {
+                let digit = digit(character)?;
+                if digit >= 32 {
+                    return Err(invalid("column digits go up to v"));
+                }
+                for row in (0..5).filter(|row| digit & 1 << row != 0) {
+                    let row = strip
+                        .checked_mul(5)
+                        .and_then(|top| top.checked_add(row))
+                        .ok_or_else(|| invalid("too large"))?;
+                    cells.push((column, row));
+                }
+                1
+            }
+        };
+        column = column
+            .checked_add(empty_columns)
+            .ok_or_else(|| invalid("too large"))?;
+    }
+    Ok(cells)
+}
+
+/// Add the cells of an object in the middle of the grid, leaving out those
+/// that don't fit
+pub fn place(grid: &mut Grid, cells: &[(u16, u16)]) {
+    let columns = cells
+        .iter()
+        .map(|(column, _)| column + 1)
+        .max()
+        .unwrap_or_default();
+    let rows = cells
+        .iter()
+        .map(|(_, row)| row + 1)
+        .max()
+        .unwrap_or_default();
+    let left = (grid.width as i32 - columns as i32) / 2;
+    let top = (grid.height as i32 - rows as i32) / 2;
+    for (column, row) in cells {
+        let width = left + *column as i32;
+        let height = top + *row as i32;
+        if (0..grid.width as i32).contains(&width) && (0..grid.height as i32).contains(&height) {
+            grid.set_cell((width as u16, height as u16), ALIVE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apgcode_of(cells: &[(u16, u16)]) -> Option<String> {
+        let mut grid = Grid::new(30, 30);
+        for (width, height) in cells {
+            grid.cycle_cell((width + 10, height + 10));
+        }
+        apgcode(&grid)
+    }
+
+    #[test]
+    fn objects_are_classified() {
+        assert_eq!(
+            apgcode_of(&[(0, 0), (1, 0), (0, 1), (1, 1)]).as_deref(),
+            Some("xs4_33")
+        );
+        let beehive = [(1, 0), (2, 0), (0, 1), (3, 1), (1, 2), (2, 2)];
+        assert_eq!(apgcode_of(&beehive).as_deref(), Some("xs6_696"));
+        assert_eq!(
+            apgcode_of(&[(0, 0), (1, 0), (2, 0)]).as_deref(),
+            Some("xp2_7")
+        );
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        assert_eq!(apgcode_of(&glider).as_deref(), Some("xq4_153"));
+        assert_eq!(apgcode_of(&[]).as_deref(), Some("xs0_0"));
+
+        // The R-pentomino keeps growing
+        assert_eq!(apgcode_of(&[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]), None);
+    }
+
+    #[test]
+    fn apgcodes_are_placed_on_the_grid() {
+        // A pentadecathlon, whose cells span two strips
+        let cells = parse("xp15_4r4z4r4").unwrap();
+        assert_eq!(cells.len(), 12);
+        let mut grid = Grid::new(30, 30);
+        place(&mut grid, &cells);
+        assert_eq!(apgcode(&grid).as_deref(), Some("xp15_4r4z4r4"));
+
+        for apgcode in ["xq4", "ab4_153", "xq4_15!", "xs4_3y"] {
+            assert!(parse(apgcode).is_err(), "{apgcode} should be invalid");
+        }
+    }
+}
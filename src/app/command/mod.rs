@@ -0,0 +1,355 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use game_of_life::grid::Topology;
+
+use super::keymap::Action;
+use super::keymap::Key;
+use super::keymap::KeyCode;
+
+// The commands that take arguments, with their usage. The actions of the key
+// map are commands too, by their name: :pause, :snapshot, :clear.
+pub const COMMANDS: [(&str, &str); 8] = [
+    ("rule", "RULE"),
+    ("load", "FILE.rle|FILE.mc"),
+    ("save", "FILE.rle|FILE.mc"),
+    ("step", "GENERATIONS"),
+    ("goto", "GENERATION"),
+    ("speed", "1-100"),
+    ("random", "[DENSITY] [seed=SEED]"),
+    ("topology", "plane|torus"),
+];
+
+// The action of a command, or why it has none
+pub fn parse(text: &str) -> Result<Action, String> {
+    let text = text.trim();
+    let (name, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let rest = rest.trim();
+    let arguments: Vec<&str> = rest.split_whitespace().collect();
+    let usage = || match COMMANDS.iter().find(|(command, _)| *command == name) {
+        Some((command, arguments)) => format!("Usage: :{command} {arguments}"),
+        None => format!(":{name} takes no arguments"),
+    };
+
+    match (name, arguments.as_slice()) {
+        ("rule", [rule]) => Ok(Action::Rule(rule.to_string())),
+        // Paths are the rest of the line, spaces included
+        ("load", [_, ..]) => Ok(Action::Load(PathBuf::from(rest))),
+        ("save", [_, ..]) => Ok(Action::Save(PathBuf::from(rest))),
+        ("step", [generations]) => Ok(Action::Step(generations.parse().map_err(|_| usage())?)),
+        ("goto", [generation]) => Ok(Action::Goto(generation.parse().map_err(|_| usage())?)),
+        ("speed", [speed]) => match speed.parse().map_err(|_| usage())? {
+            speed @ 1..=100 => Ok(Action::Speed(speed)),
+            _ => Err(usage()),
+        },
+        ("random", arguments) if arguments.len() <= 2 => {
+            let (mut density, mut seed) = (0.5, None);
+            for argument in arguments {
+                match argument.strip_prefix("seed=") {
+                    Some(value) => seed = Some(value.parse().map_err(|_| usage())?),
+                    None => density = argument.parse().map_err(|_| usage())?,
+                }
+            }
+            if !(0.0..=1.0).contains(&density) {
+                return Err("The density is between 0 and 1".to_string());
+            }
+            Ok(Action::Random { density, seed })
+        }
+        ("topology", [name]) => Topology::from_name(name)
+            .map(Action::Topology)
+            .ok_or_else(usage),
+        _ if COMMANDS.iter().any(|(command, _)| *command == name) => Err(usage()),
+        _ => match Action::from_name(name) {
+            Some(action) if arguments.is_empty() => Ok(action),
+            Some(_) => Err(usage()),
+            None => Err(format!("Unknown command :{name}")),
+        },
+    }
+}
+
+// The names that can be typed after :
+fn names() -> Vec<String> {
+    let actions = Action::ALL
+        .into_iter()
+        .filter(|action| !matches!(action, Action::Paint(_) | Action::Command))
+        .map(|action| action.name());
+    COMMANDS
+        .iter()
+        .map(|(command, _)| command.to_string())
+        .chain(actions)
+        .collect()
+}
+
+// What a key does to the command line
+#[derive(PartialEq, Eq, Debug)]
+pub enum Edit {
+    Changed,
+    Closed,
+    // Enter runs the command
+    Entered(String),
+}
+
+// The line a command is typed on, with the commands typed before it. Up and
+// down go through them, and tab completes the names of commands and of files.
+#[derive(Default)]
+pub struct CommandLine {
+    is_open: bool,
+    text: String,
+    history: Vec<String>,
+    // Where up and down are in the history, and what was typed before
+    history_position: Option<usize>,
+    draft: String,
+    // What a tab could complete, when there is more than one choice
+    completions: Vec<String>,
+}
+
+impl CommandLine {
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.text.clear();
+        self.history_position = None;
+        self.completions.clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    // The line as it is shown, with the choices of the last completion
+    pub fn line(&self) -> String {
+        let mut line = format!(":{}█", self.text);
+        if !self.completions.is_empty() {
+            line.push_str("    ");
+            line.push_str(&self.completions.join("  "));
+        }
+        line
+    }
+
+    pub fn edit(&mut self, key: Key) -> Edit {
+        self.completions.clear();
+        match key.code {
+            KeyCode::Char(character) if !key.control && !key.alt => self.text.push(character),
+            KeyCode::Backspace if self.text.is_empty() => return self.close(),
+            KeyCode::Backspace => {
+                self.text.pop();
+            }
+            KeyCode::Escape => return self.close(),
+            KeyCode::Enter => {
+                let text = self.text.trim().to_string();
+                if !text.is_empty() && self.history.last() != Some(&text) {
+                    self.history.push(text.clone());
+                }
+                self.close();
+                return Edit::Entered(text);
+            }
+            KeyCode::Tab => self.complete(),
+            KeyCode::Up => self.move_in_history(-1),
+            KeyCode::Down => self.move_in_history(1),
+            _ => {}
+        }
+        Edit::Changed
+    }
+
+    // Pasted text is typed on the line, up to its end
+    pub fn paste(&mut self, text: &str) {
+        self.text.push_str(text.lines().next().unwrap_or(""));
+    }
+
+    // Add to the last command what running it drew, as if it had been typed
+    // after it
+    pub fn complete_last(&mut self, text: &str) {
+        let completed = format!("{}{text}", self.text).trim().to_string();
+        if let Some(last) = self.history.last_mut() {
+            *last = completed;
+        }
+    }
+
+    fn close(&mut self) -> Edit {
+        self.is_open = false;
+        Edit::Closed
+    }
+
+    fn move_in_history(&mut self, step: isize) {
+        let position = match self.history_position {
+            None if step < 0 => {
+                self.draft = self.text.clone();
+                self.history.len().checked_sub(1)
+            }
+            None => return,
+            Some(position) => position.checked_add_signed(step),
+        };
+        match position.filter(|position| *position < self.history.len()) {
+            Some(position) => {
+                self.history_position = Some(position);
+                self.text = self.history[position].clone();
+            }
+            // Down after the last command brings back what was typed
+            None if step > 0 => {
+                self.history_position = None;
+                self.text = self.draft.clone();
+            }
+            None => {}
+        }
+    }
+
+    // Complete the word being typed as far as all its choices go, and show
+    // them when there are several
+    fn complete(&mut self) {
+        let (start, word) = match self.text.split_once(' ') {
+            None => ("", self.text.as_str()),
+            Some((name, argument)) => (name, argument.trim_start()),
+        };
+        let choices: Vec<String> = match start {
+            "" => names(),
+            "topology" => Topology::ALL
+                .iter()
+                .map(|topology| topology.name().to_string())
+                .collect(),
+            "load" | "save" => files(word),
+            _ => Vec::new(),
+        };
+        let choices: Vec<String> = choices
+            .into_iter()
+            .filter(|choice| choice.starts_with(word))
+            .collect();
+        let Some(first) = choices.first() else {
+            return;
+        };
+
+        let mut completed = first.clone();
+        for choice in &choices[1..] {
+            while !choice.starts_with(&completed) {
+                completed.pop();
+            }
+        }
+        // A whole name is followed by its arguments
+        if choices.len() == 1 && start.is_empty() {
+            completed.push(' ');
+        }
+        self.text = match start {
+            "" => completed,
+            _ => format!("{start} {completed}"),
+        };
+        if choices.len() > 1 {
+            self.completions = choices
+                .iter()
+                .map(|choice| file_name(choice).to_string())
+                .collect();
+        }
+    }
+}
+
+// The files and directories in the directory of a path, as paths
+fn files(path: &str) -> Vec<String> {
+    let directory = match path.rfind('/') {
+        Some(end) => &path[..=end],
+        None => "",
+    };
+    let Ok(entries) = fs::read_dir(if directory.is_empty() { "." } else { directory }) else {
+        return Vec::new();
+    };
+    let mut files: Vec<String> = entries
+        .flatten()
+        .map(|entry| {
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            format!("{directory}{}{slash}", entry.file_name().to_string_lossy())
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+// Choices that are paths are shown by the name of their file
+fn file_name(choice: &str) -> &str {
+    let trimmed = choice.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(start) => &choice[start + 1..],
+        None => choice,
+    }
+}
+
+// Whether a path names a pattern file the command line can load and save
+pub fn pattern_format(path: &Path) -> Option<&str> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| matches!(*extension, "rle" | "mc"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(command_line: &mut CommandLine, keys: &str) -> Edit {
+        let mut edit = Edit::Changed;
+        for key in keys.split_whitespace() {
+            edit = command_line.edit(key.parse().unwrap());
+        }
+        edit
+    }
+
+    #[test]
+    fn commands_are_actions() {
+        assert_eq!(
+            parse("rule B36/S23"),
+            Ok(Action::Rule("B36/S23".to_string()))
+        );
+        assert_eq!(
+            parse("load my pattern.rle"),
+            Ok(Action::Load(PathBuf::from("my pattern.rle")))
+        );
+        assert_eq!(parse("step 100"), Ok(Action::Step(100)));
+        assert_eq!(parse("goto 5000"), Ok(Action::Goto(5000)));
+        assert_eq!(parse("speed 20"), Ok(Action::Speed(20)));
+        assert_eq!(
+            parse("random 0.3 seed=42"),
+            Ok(Action::Random {
+                density: 0.3,
+                seed: Some(42)
+            })
+        );
+        assert_eq!(
+            parse("topology torus"),
+            Ok(Action::Topology(Topology::Torus))
+        );
+        assert_eq!(parse("clear"), Ok(Action::Clear));
+        assert_eq!(parse("pause"), Ok(Action::Pause));
+
+        assert_eq!(parse("step"), Err("Usage: :step GENERATIONS".to_string()));
+        assert_eq!(parse("speed 0"), Err("Usage: :speed 1-100".to_string()));
+        assert!(parse("random 2").is_err());
+        assert!(parse("clear all").is_err());
+        assert_eq!(parse("jump"), Err("Unknown command :jump".to_string()));
+    }
+
+    #[test]
+    fn the_command_line_completes_and_remembers() {
+        let mut command_line = CommandLine::default();
+        command_line.open();
+        type_keys(&mut command_line, "s t tab");
+        assert_eq!(command_line.line(), ":st█    step  statistics");
+        type_keys(&mut command_line, "e tab 1 0");
+        assert_eq!(command_line.line(), ":step 10█");
+        assert_eq!(
+            type_keys(&mut command_line, "enter"),
+            Edit::Entered("step 10".to_string())
+        );
+        assert!(!command_line.is_open());
+
+        command_line.open();
+        type_keys(&mut command_line, "t o p tab t tab");
+        assert_eq!(command_line.line(), ":topology torus█");
+        type_keys(&mut command_line, "enter");
+
+        // Up goes back through the commands, down comes back to the line
+        command_line.open();
+        type_keys(&mut command_line, "c up up");
+        assert_eq!(command_line.line(), ":step 10█");
+        type_keys(&mut command_line, "down down");
+        assert_eq!(command_line.line(), ":c█");
+        assert_eq!(
+            type_keys(&mut command_line, "backspace backspace"),
+            Edit::Closed
+        );
+    }
+}
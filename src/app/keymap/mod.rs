@@ -0,0 +1,433 @@
+use std::env;
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use game_of_life::grid::Topology;
+
+use crate::exit::Error;
+
+// A key pressed with its modifiers. Shift is part of the character: P is
+// shift+p.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Key {
+    pub code: KeyCode,
+    pub control: bool,
+    pub alt: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// The names of the keys that aren't written as their character. Space and
+// comma have names so bindings can be separated by them.
+const KEY_NAMES: [(&str, KeyCode); 10] = [
+    ("space", KeyCode::Char(' ')),
+    ("comma", KeyCode::Char(',')),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Escape),
+    ("backspace", KeyCode::Backspace),
+    ("tab", KeyCode::Tab),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+];
+
+impl Key {
+    pub fn new(code: KeyCode) -> Self {
+        Key {
+            code,
+            control: false,
+            alt: false,
+        }
+    }
+}
+
+impl From<char> for Key {
+    fn from(character: char) -> Self {
+        Key::new(KeyCode::Char(character))
+    }
+}
+
+// Keys are written as ctrl+alt+x, the name of the key after its modifiers
+impl Display for Key {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.control {
+            formatter.write_str("ctrl+")?;
+        }
+        if self.alt {
+            formatter.write_str("alt+")?;
+        }
+        match KEY_NAMES.iter().find(|(_, code)| *code == self.code) {
+            Some((name, _)) => formatter.write_str(name),
+            None => match self.code {
+                KeyCode::Char(character) => write!(formatter, "{character}"),
+                _ => unreachable!("every other key has a name"),
+            },
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (mut control, mut alt) = (false, false);
+        let mut name = text;
+        loop {
+            if let Some(rest) = name.strip_prefix("ctrl+").filter(|rest| !rest.is_empty()) {
+                (control, name) = (true, rest);
+            } else if let Some(rest) = name.strip_prefix("alt+").filter(|rest| !rest.is_empty()) {
+                (alt, name) = (true, rest);
+            } else {
+                break;
+            }
+        }
+        let mut characters = name.chars();
+        let code = match (characters.next(), characters.next()) {
+            (Some(character), None) => KeyCode::Char(character),
+            _ => KEY_NAMES
+                .iter()
+                .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+                .map(|(_, code)| *code)
+                .ok_or(())?,
+        };
+        Ok(Key { code, control, alt })
+    }
+}
+
+// What keys and commands do in the game
+#[derive(Clone, PartialEq, Debug)]
+pub enum Action {
+    Quit,
+    Pause,
+    Faster,
+    Slower,
+    Record,
+    Snapshot,
+    Statistics,
+    ExportCsv,
+    Apgcode,
+    CycleAnchor,
+    Clear,
+    // Open the command line
+    Command,
+    Help,
+    // Clicks paint this species, for automata with several species
+    Paint(u8),
+    // The actions of commands, which have arguments keys can't give
+    Rule(String),
+    Load(PathBuf),
+    Save(PathBuf),
+    Step(usize),
+    Goto(usize),
+    Speed(u8),
+    Random { density: f64, seed: Option<u64> },
+    Topology(Topology),
+}
+
+impl Action {
+    // In the order of the help
+    pub const ALL: [Action; 22] = [
+        Action::Quit,
+        Action::Pause,
+        Action::Faster,
+        Action::Slower,
+        Action::Record,
+        Action::Snapshot,
+        Action::Statistics,
+        Action::ExportCsv,
+        Action::Apgcode,
+        Action::CycleAnchor,
+        Action::Clear,
+        Action::Command,
+        Action::Help,
+        Action::Paint(1),
+        Action::Paint(2),
+        Action::Paint(3),
+        Action::Paint(4),
+        Action::Paint(5),
+        Action::Paint(6),
+        Action::Paint(7),
+        Action::Paint(8),
+        Action::Paint(9),
+    ];
+
+    // How the action is named in key maps and commands
+    pub fn name(&self) -> String {
+        match self {
+            Action::Quit => "quit".to_string(),
+            Action::Pause => "pause".to_string(),
+            Action::Faster => "faster".to_string(),
+            Action::Slower => "slower".to_string(),
+            Action::Record => "record".to_string(),
+            Action::Snapshot => "snapshot".to_string(),
+            Action::Statistics => "statistics".to_string(),
+            Action::ExportCsv => "csv".to_string(),
+            Action::Apgcode => "apgcode".to_string(),
+            Action::CycleAnchor => "anchor".to_string(),
+            Action::Clear => "clear".to_string(),
+            Action::Command => "command".to_string(),
+            Action::Help => "help".to_string(),
+            Action::Paint(species) => format!("paint-{species}"),
+            Action::Rule(_) => "rule".to_string(),
+            Action::Load(_) => "load".to_string(),
+            Action::Save(_) => "save".to_string(),
+            Action::Step(_) => "step".to_string(),
+            Action::Goto(_) => "goto".to_string(),
+            Action::Speed(_) => "speed".to_string(),
+            Action::Random { .. } => "random".to_string(),
+            Action::Topology(_) => "topology".to_string(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Action::Quit => "Quit".to_string(),
+            Action::Pause => "Pause or resume".to_string(),
+            Action::Faster => "Speed up".to_string(),
+            Action::Slower => "Slow down".to_string(),
+            Action::Record => "Start or stop recording a GIF".to_string(),
+            Action::Snapshot => "Save the grid as PNG and SVG".to_string(),
+            Action::Statistics => "Show or hide the statistics".to_string(),
+            Action::ExportCsv => "Save the statistics as CSV".to_string(),
+            Action::Apgcode => "Show the apgcode of the pattern".to_string(),
+            Action::CycleAnchor => "Change the resize anchor".to_string(),
+            Action::Clear => "Clear the grid".to_string(),
+            Action::Command => "Type a command".to_string(),
+            Action::Help => "Show or hide this help".to_string(),
+            Action::Paint(species) => format!("Paint species {species}"),
+            Action::Rule(rule) => format!("Change the rule to {rule}"),
+            Action::Load(path) => format!("Load {}", path.display()),
+            Action::Save(path) => format!("Save the grid as {}", path.display()),
+            Action::Step(generations) => format!("Go {generations} generations forward"),
+            Action::Goto(generation) => format!("Go to generation {generation}"),
+            Action::Speed(speed) => format!("Play at speed {speed}"),
+            Action::Random { density, .. } => format!("Fill {density} of the grid at random"),
+            Action::Topology(topology) => format!("Play on a {}", topology.name()),
+        }
+    }
+
+    // The actions that keys can be bound to, by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+// Where a sequence of keys leads in a key map
+#[derive(PartialEq, Debug)]
+pub enum Lookup {
+    Action(Action),
+    // The start of a longer sequence, which waits for its next key
+    Prefix,
+    None,
+}
+
+// The actions of key sequences, most of them a single key
+#[derive(Clone, PartialEq, Debug)]
+pub struct KeyMap {
+    bindings: Vec<(Vec<Key>, Action)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let keys = [
+            ('q', Action::Quit),
+            ('p', Action::Pause),
+            ('+', Action::Faster),
+            ('-', Action::Slower),
+            ('r', Action::Record),
+            ('s', Action::Snapshot),
+            ('i', Action::Statistics),
+            ('c', Action::ExportCsv),
+            ('a', Action::Apgcode),
+            ('o', Action::CycleAnchor),
+            (':', Action::Command),
+            ('?', Action::Help),
+        ];
+        let digits = ('1'..='9')
+            .zip(1..)
+            .map(|(digit, species)| (digit, Action::Paint(species)));
+        KeyMap {
+            bindings: keys
+                .into_iter()
+                .chain(digits)
+                .map(|(key, action)| (vec![Key::from(key)], action))
+                .collect(),
+        }
+    }
+}
+
+// Key maps change the default bindings with one line per action: its name,
+// then its key sequences separated by commas, and the keys of a sequence
+// separated by spaces. An action with nothing after it is unbound.
+//
+// pause = p, space
+// snapshot = ctrl+x s
+// csv =
+impl FromStr for KeyMap {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut keymap = KeyMap::default();
+        for (line_number, line) in (1..).zip(text.lines()) {
+            let invalid = |message: String| {
+                Error::Config(format!("line {line_number} of the key map: {message}"))
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, sequences) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected ACTION = KEYS, found \"{line}\"")))?;
+            let action = Action::from_name(name.trim())
+                .ok_or_else(|| invalid(format!("unknown action \"{}\"", name.trim())))?;
+            keymap.bindings.retain(|(_, bound)| *bound != action);
+            for sequence in sequences
+                .split(',')
+                .filter(|sequence| !sequence.trim().is_empty())
+            {
+                let keys = sequence
+                    .split_whitespace()
+                    .map(|key| {
+                        key.parse()
+                            .map_err(|_| invalid(format!("unknown key \"{key}\"")))
+                    })
+                    .collect::<Result<Vec<Key>, Error>>()?;
+                // A sequence moves to the action that is bound to it last
+                keymap.bindings.retain(|(bound, _)| *bound != keys);
+                keymap.bindings.push((keys, action.clone()));
+            }
+        }
+
+        // A sequence that starts another one would never let it be typed
+        for (keys, action) in &keymap.bindings {
+            for (longer_keys, longer_action) in &keymap.bindings {
+                if longer_keys.len() > keys.len() && longer_keys.starts_with(keys) {
+                    return Err(Error::Config(format!(
+                        "the key map binds {} to {} and {} to {}",
+                        sequence_name(keys),
+                        action.name(),
+                        sequence_name(longer_keys),
+                        longer_action.name()
+                    )));
+                }
+            }
+        }
+        Ok(keymap)
+    }
+}
+
+// Every action with its key sequences, which reads back as the same key map
+impl Display for KeyMap {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for action in Action::ALL {
+            writeln!(
+                formatter,
+                "{} = {}",
+                action.name(),
+                self.bindings(&action).join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl KeyMap {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut lookup = Lookup::None;
+        for (bound, action) in &self.bindings {
+            if bound == keys {
+                return Lookup::Action(action.clone());
+            }
+            if bound.starts_with(keys) {
+                lookup = Lookup::Prefix;
+            }
+        }
+        lookup
+    }
+
+    // The key sequences of an action, as they are written in key maps
+    pub fn bindings(&self, action: &Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| bound == action)
+            .map(|(keys, _)| sequence_name(keys))
+            .collect()
+    }
+}
+
+fn sequence_name(keys: &[Key]) -> String {
+    keys.iter()
+        .map(Key::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Key maps are read from the configuration directory unless one is given
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("game-of-life").join("keys"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(text: &str) -> Vec<Key> {
+        text.split_whitespace()
+            .map(|key| key.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn key_maps_rebind_actions() {
+        let keymap: KeyMap = "# Pause with the space bar too\n\
+                              pause = p, space\n\
+                              snapshot = ctrl+x s, alt+S\n\
+                              faster = q\n\
+                              csv =\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(keymap.lookup(&keys("space")), Lookup::Action(Action::Pause));
+        assert_eq!(keymap.lookup(&keys("ctrl+x")), Lookup::Prefix);
+        assert_eq!(
+            keymap.lookup(&keys("ctrl+x s")),
+            Lookup::Action(Action::Snapshot)
+        );
+        assert_eq!(keymap.lookup(&keys("s")), Lookup::None);
+        assert_eq!(keymap.lookup(&keys("q")), Lookup::Action(Action::Faster));
+        assert_eq!(keymap.bindings(&Action::Snapshot), ["ctrl+x s", "alt+S"]);
+        assert!(keymap.bindings(&Action::Quit).is_empty());
+        assert!(keymap.bindings(&Action::ExportCsv).is_empty());
+        assert_eq!(keymap.bindings(&Action::Paint(3)), ["3"]);
+
+        assert!("pause = ctrl+".parse::<KeyMap>().is_err());
+        assert!("jump = j".parse::<KeyMap>().is_err());
+        // q would quit before q q could be typed
+        assert!("help = q q".parse::<KeyMap>().is_err());
+    }
+}
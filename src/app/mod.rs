@@ -0,0 +1,709 @@
+pub mod command;
+pub mod keymap;
+pub mod script;
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use game_of_life::apgcode;
+use game_of_life::automaton;
+use game_of_life::automaton::Automaton;
+use game_of_life::export::save_png;
+use game_of_life::export::save_svg;
+use game_of_life::export::GifRecorder;
+use game_of_life::export::ImageStyle;
+use game_of_life::export::Region;
+use game_of_life::grid::ALIVE;
+use game_of_life::macrocell;
+use game_of_life::random::Random;
+use game_of_life::render::Renderer;
+use game_of_life::rle;
+use game_of_life::session::Session;
+use game_of_life::statistics::Statistics;
+use game_of_life::Grid;
+
+use crate::exit::Error;
+use crate::printer::cell_at;
+use crate::printer::grid_width;
+use crate::printer::print_cell;
+use crate::printer::print_cells;
+use crate::printer::print_help;
+use crate::printer::print_message;
+use crate::printer::print_ribbon_bottom;
+use crate::printer::print_ribbon_top;
+use crate::printer::print_statistics;
+use crate::printer::print_too_small;
+use crate::printer::PANEL_WIDTH;
+use command::CommandLine;
+use command::Edit;
+use keymap::Action;
+use keymap::Key;
+use keymap::KeyMap;
+use keymap::Lookup;
+
+pub const TOP_MARGIN: u16 = 2;
+pub const BOTTOM_MARGIN: u16 = 1;
+pub const VERTICAL_MARGIN: u16 = TOP_MARGIN + BOTTOM_MARGIN;
+
+// The game is suspended in smaller terminals
+pub const MIN_COLUMNS: u16 = 20;
+pub const MIN_ROWS: u16 = VERTICAL_MARGIN + 5;
+
+// :step and :goto go this many generations at a time, this often, so the game
+// keeps reading keys on long jumps
+const JUMP_CHUNK: usize = 256;
+const JUMP_INTERVAL: Duration = Duration::from_millis(10);
+
+// What the player does, whatever reads it: the terminal or a script
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Input {
+    Key(Key),
+    // Ctrl+C
+    Interrupt,
+    // A left click on a column of a row of the screen
+    Click { column: u16, row: u16 },
+    Paste(String),
+    Resize { columns: u16, rows: u16 },
+}
+
+// What changed on the screen and needs to be drawn again
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Draw {
+    Cells,
+    Cell((u16, u16)),
+    RibbonTop,
+    RibbonBottom,
+    Message(String),
+    // The help over the cells
+    Help,
+    // The whole screen says the terminal is too small
+    TooSmall,
+}
+
+// The game without its terminal: inputs change the game and tell what to draw,
+// and generations advance when the clock reaches them. Times are durations
+// since the start of the game, so a game driven by a script is repeatable.
+pub struct App {
+    pub grid: Grid,
+    pub delay: u8,
+    pub game_is_paused: bool,
+    pub keymap: KeyMap,
+    // The keys of a sequence typed so far
+    pending_keys: Vec<Key>,
+    statistics: Statistics,
+    // The statistics side panel is hidden until toggled
+    panel_is_visible: bool,
+    // Generations are written to a GIF while recording
+    recorder: Option<GifRecorder>,
+    // Clicks paint this species, for automata with several species
+    species: u8,
+    // Messages replace the help ribbon until the next key press
+    message_is_shown: bool,
+    // The help stays over the cells until the next key press
+    help_is_shown: bool,
+    // Keys type on the command line while it is open
+    command_line: CommandLine,
+    // The generation a :step or :goto is going to, until a key stops it
+    jump_target: Option<usize>,
+    // The seed the last :random drew, until the script of the game takes it
+    drawn_seed: Option<u64>,
+    terminal_width: u16,
+    terminal_height: u16,
+    last_generation: Duration,
+    has_quit: bool,
+}
+
+impl App {
+    pub fn new(grid: Grid, delay: u8, game_is_paused: bool, (columns, rows): (u16, u16)) -> Self {
+        App {
+            statistics: Statistics::new(&grid),
+            grid,
+            delay,
+            game_is_paused,
+            keymap: KeyMap::default(),
+            pending_keys: Vec::new(),
+            panel_is_visible: false,
+            recorder: None,
+            species: ALIVE,
+            message_is_shown: false,
+            help_is_shown: false,
+            command_line: CommandLine::default(),
+            jump_target: None,
+            drawn_seed: None,
+            terminal_width: columns,
+            terminal_height: rows,
+            last_generation: Duration::ZERO,
+            has_quit: false,
+        }
+    }
+
+    // The whole screen, as it is first drawn
+    pub fn start(&self) -> Vec<Draw> {
+        if self.is_too_small() {
+            return vec![Draw::TooSmall];
+        }
+        vec![Draw::Cells, Draw::RibbonBottom, Draw::RibbonTop]
+    }
+
+    pub fn is_too_small(&self) -> bool {
+        self.terminal_width < MIN_COLUMNS || self.terminal_height < MIN_ROWS
+    }
+
+    pub fn has_quit(&self) -> bool {
+        self.has_quit
+    }
+
+    // The paste that types the seed the last :random drew after its command,
+    // for the script of the game to replay the same cells
+    pub fn take_drawn_seed(&mut self) -> Option<Input> {
+        self.drawn_seed
+            .take()
+            .map(|seed| Input::Paste(seed_argument(seed)))
+    }
+
+    pub fn handle(&mut self, input: Input, now: Duration) -> Result<Vec<Draw>, Error> {
+        let mut draws = Vec::new();
+        let action = match input {
+            Input::Key(key) if !self.command_line.is_open() => self.key_action(key),
+            Input::Interrupt => Some(Action::Quit),
+            _ => None,
+        };
+        // Only quitting and resizing work in a terminal that is too small
+        if self.is_too_small()
+            && action != Some(Action::Quit)
+            && !matches!(input, Input::Resize { .. })
+        {
+            return Ok(draws);
+        }
+        if self.message_is_shown && matches!(input, Input::Key(_) | Input::Interrupt) {
+            self.message_is_shown = false;
+            draws.push(Draw::RibbonBottom);
+        }
+        // A key stops a jump where it is
+        if self.jump_target.is_some() && matches!(input, Input::Key(_)) {
+            self.jump_target = None;
+            self.last_generation = now;
+            return Ok(draws);
+        }
+        if self.help_is_shown && matches!(input, Input::Key(_)) {
+            self.help_is_shown = false;
+            self.pending_keys.clear();
+            draws.push(Draw::Cells);
+            return Ok(draws);
+        }
+        match input {
+            Input::Key(key) if self.command_line.is_open() => match self.command_line.edit(key) {
+                Edit::Changed | Edit::Closed => draws.push(Draw::RibbonBottom),
+                Edit::Entered(text) => {
+                    draws.push(Draw::RibbonBottom);
+                    self.run_command(&text, now, &mut draws);
+                }
+            },
+            Input::Paste(text) if self.command_line.is_open() => {
+                self.command_line.paste(&text);
+                draws.push(Draw::RibbonBottom);
+            }
+            // An export that fails says why, and the game goes on
+            Input::Key(_) | Input::Interrupt => {
+                if let Some(action) = action {
+                    let description = action.description();
+                    if let Err(error) = self.perform(action, now, &mut draws) {
+                        self.message_is_shown = true;
+                        draws.push(Draw::Message(format!("{description}: {error}")));
+                    }
+                }
+            }
+            // Mouse editing is only available while the game is paused
+            Input::Click { column, row } if self.game_is_paused => {
+                let is_under_panel = self.panel_is_visible
+                    && column >= self.terminal_width.saturating_sub(PANEL_WIDTH);
+                if row < TOP_MARGIN
+                    || row >= self.terminal_height.saturating_sub(BOTTOM_MARGIN)
+                    || is_under_panel
+                {
+                    return Ok(draws);
+                }
+                if let Some(index) = cell_at(&self.grid, column, row - TOP_MARGIN) {
+                    if self.grid.automaton().species() > 1 {
+                        self.grid.paint_cell(index, self.species);
+                    } else {
+                        self.grid.cycle_cell(index);
+                    }
+                    draws.extend([Draw::Cell(index), Draw::RibbonTop]);
+                }
+            }
+            Input::Click { .. } => {}
+            // Pasting an apgcode adds its object in the middle of the grid
+            Input::Paste(text) => match apgcode::parse(&text) {
+                Ok(cells) => {
+                    apgcode::place(&mut self.grid, &cells);
+                    draws.extend([Draw::Cells, Draw::RibbonTop]);
+                }
+                Err(error) => {
+                    self.message_is_shown = true;
+                    draws.push(Draw::Message(error.to_string()));
+                }
+            },
+            Input::Resize { columns, rows } => {
+                let was_too_small = self.is_too_small();
+                self.terminal_width = columns;
+                self.terminal_height = rows;
+                if self.is_too_small() {
+                    draws.push(Draw::TooSmall);
+                    return Ok(draws);
+                }
+                // The game resumes where it was suspended
+                if was_too_small {
+                    self.last_generation = now;
+                }
+                self.grid.resize(
+                    grid_width(columns, self.grid.automaton()),
+                    rows.saturating_sub(VERTICAL_MARGIN),
+                );
+                draws.extend([Draw::Cells, Draw::RibbonTop, Draw::RibbonBottom]);
+            }
+        }
+        Ok(draws)
+    }
+
+    // The action of a key, when it completes a sequence of the key map. A key
+    // that doesn't continue the sequence drops it.
+    fn key_action(&mut self, key: Key) -> Option<Action> {
+        self.pending_keys.push(key);
+        match self.keymap.lookup(&self.pending_keys) {
+            Lookup::Action(action) => {
+                self.pending_keys.clear();
+                Some(action)
+            }
+            Lookup::Prefix => None,
+            Lookup::None => {
+                self.pending_keys.clear();
+                None
+            }
+        }
+    }
+
+    // Commands that can't be parsed or performed say why instead of the help
+    // ribbon, and the game goes on
+    fn run_command(&mut self, text: &str, now: Duration, draws: &mut Vec<Draw>) {
+        if text.is_empty() {
+            return;
+        }
+        let message = match command::parse(text) {
+            Ok(action) => match self.perform(action, now, draws) {
+                Ok(()) => return,
+                Err(error) => format!(":{text}: {error}"),
+            },
+            Err(message) => message,
+        };
+        self.message_is_shown = true;
+        draws.push(Draw::Message(message));
+    }
+
+    fn perform(
+        &mut self,
+        action: Action,
+        now: Duration,
+        draws: &mut Vec<Draw>,
+    ) -> Result<(), Error> {
+        match action {
+            Action::Quit => self.has_quit = true,
+            Action::Pause => {
+                self.game_is_paused = !self.game_is_paused;
+                // The next generation comes a whole interval after resuming
+                self.last_generation = now;
+            }
+            Action::Record => {
+                // The ribbon stops showing the recording even when it fails
+                draws.push(Draw::RibbonTop);
+                match self.recorder.take() {
+                    Some(recorder) => recorder.finish()?,
+                    None => {
+                        let mut recorder = GifRecorder::create(
+                            &export_path("gif"),
+                            self.grid.width,
+                            self.grid.height,
+                            ImageStyle::default(),
+                        )?;
+                        recorder.add_frame(&self.grid, generation_interval(self.delay))?;
+                        self.recorder = Some(recorder);
+                    }
+                }
+            }
+            Action::Snapshot => {
+                let region = Region::whole(&self.grid);
+                let style = ImageStyle::default();
+                save_png(&export_path("png"), &self.grid, &region, &style)?;
+                save_svg(&export_path("svg"), &self.grid, &region, &style)?;
+            }
+            Action::Statistics => {
+                self.panel_is_visible = !self.panel_is_visible;
+                draws.push(Draw::Cells);
+            }
+            Action::ExportCsv => self.statistics.save_csv(&export_path("csv"))?,
+            Action::Apgcode => {
+                let message = match apgcode::apgcode(&self.grid) {
+                    Some(apgcode) => format!("apgcode: {apgcode}"),
+                    None => "Not a still life, oscillator or spaceship that fits in 40 by 40 cells"
+                        .to_string(),
+                };
+                self.message_is_shown = true;
+                draws.push(Draw::Message(message));
+            }
+            // The anchor of resizes cycles through all of them
+            Action::CycleAnchor => {
+                self.grid.anchor = self.grid.anchor.next();
+                self.message_is_shown = true;
+                draws.push(Draw::Message(format!(
+                    "Resize anchor: {}",
+                    self.grid.anchor.name()
+                )));
+            }
+            Action::Faster if self.delay > 0 => {
+                self.delay -= 1;
+                draws.push(Draw::RibbonTop);
+            }
+            Action::Slower if self.delay < 99 => {
+                self.delay += 1;
+                draws.push(Draw::RibbonTop);
+            }
+            Action::Faster | Action::Slower => {}
+            Action::Help => {
+                self.help_is_shown = true;
+                draws.push(Draw::Help);
+            }
+            Action::Paint(species) => {
+                let automaton = self.grid.automaton();
+                if automaton.species() > 1 && species <= automaton.species() {
+                    self.species = species;
+                    draws.push(Draw::RibbonTop);
+                }
+            }
+            Action::Clear => {
+                self.grid.clear();
+                draws.extend([Draw::Cells, Draw::RibbonTop]);
+            }
+            Action::Command => {
+                self.command_line.open();
+                draws.push(Draw::RibbonBottom);
+            }
+            Action::Rule(rule) => {
+                self.set_automaton(automaton::parse(&rule)?);
+                draws.extend([Draw::Cells, Draw::RibbonTop]);
+            }
+            Action::Load(path) => {
+                self.load(&path)?;
+                draws.extend([Draw::Cells, Draw::RibbonTop]);
+            }
+            Action::Save(path) => {
+                let region = Region::whole(&self.grid);
+                match command::pattern_format(&path) {
+                    Some("rle") => rle::save(&path, &self.grid, &region)?,
+                    Some(_) => macrocell::save(&path, &self.grid, &region)?,
+                    None => return Err(unknown_format(&path)),
+                }
+                self.message_is_shown = true;
+                draws.push(Draw::Message(format!("Saved {}", path.display())));
+            }
+            Action::Step(generations) => {
+                let generation = self.grid.generation.saturating_add(generations);
+                self.start_jump(generation, now, draws)?;
+            }
+            Action::Goto(generation) => {
+                if generation < self.grid.generation {
+                    return Err(Error::Config(format!(
+                        "Generation {generation} has already passed",
+                    )));
+                }
+                self.start_jump(generation, now, draws)?;
+            }
+            // Speeds go from 1, the slowest delay, to 100 without any
+            Action::Speed(speed) => {
+                self.delay = 100u8.saturating_sub(speed);
+                draws.push(Draw::RibbonTop);
+            }
+            Action::Random { density, seed } => {
+                // A seed that is drawn is added to the command, so that it
+                // runs again the same from the history or from a script
+                let seed = seed.unwrap_or_else(|| {
+                    let seed = Random::time_seed();
+                    self.command_line.complete_last(&seed_argument(seed));
+                    self.drawn_seed = Some(seed);
+                    seed
+                });
+                let mut random = Random::new(seed);
+                self.grid.clear();
+                for height in 0..self.grid.height {
+                    for width in 0..self.grid.width {
+                        if random.chance(density) {
+                            self.grid.set_cell((width, height), self.species);
+                        }
+                    }
+                }
+                draws.extend([Draw::Cells, Draw::RibbonTop]);
+            }
+            Action::Topology(topology) => {
+                self.grid.topology = topology;
+                self.message_is_shown = true;
+                draws.push(Draw::Message(format!("Topology: {}", topology.name())));
+            }
+        }
+        Ok(())
+    }
+
+    // Jump to a generation, saying how to stop when it takes more than a chunk
+    fn start_jump(
+        &mut self,
+        generation: usize,
+        now: Duration,
+        draws: &mut Vec<Draw>,
+    ) -> Result<(), Error> {
+        self.jump_target = Some(generation);
+        draws.extend(self.jump(now)?);
+        if self.jump_target.is_some() {
+            self.message_is_shown = true;
+            draws.push(Draw::Message(format!(
+                "Going to generation {generation}, any key stops"
+            )));
+        }
+        Ok(())
+    }
+
+    // Advance a chunk of the generations of a jump
+    fn jump(&mut self, now: Duration) -> Result<Vec<Draw>, Error> {
+        let Some(target) = self.jump_target.take() else {
+            return Ok(Vec::new());
+        };
+        let chunk_end = target.min(self.grid.generation.saturating_add(JUMP_CHUNK));
+        while self.grid.generation < chunk_end {
+            self.advance()?;
+        }
+        if self.grid.generation < target {
+            self.jump_target = Some(target);
+        }
+        // The game goes on a whole interval after the jump
+        self.last_generation = now;
+        Ok(vec![Draw::Cells, Draw::RibbonTop])
+    }
+
+    // A new automaton can need another width of the grid, and fewer species
+    fn set_automaton(&mut self, automaton: Box<dyn Automaton>) {
+        self.grid.set_automaton(automaton);
+        self.grid.resize(
+            grid_width(self.terminal_width, self.grid.automaton()),
+            self.grid.height,
+        );
+        if self.species > self.grid.automaton().species() {
+            self.species = ALIVE;
+        }
+    }
+
+    // Loading a pattern starts a new game with its rule
+    fn load(&mut self, path: &Path) -> Result<(), Error> {
+        match command::pattern_format(path) {
+            Some("rle") => {
+                let mut pattern = rle::load(path)?;
+                if let Some(rule) = pattern.rule.take() {
+                    self.set_automaton(rule);
+                }
+                pattern.place(&mut self.grid);
+            }
+            Some(_) => {
+                let mut pattern = macrocell::load(path)?;
+                if let Some(rule) = pattern.rule.take() {
+                    self.set_automaton(rule);
+                }
+                pattern.place(&mut self.grid);
+            }
+            None => return Err(unknown_format(path)),
+        }
+        self.grid.generation = 0;
+        self.statistics = Statistics::new(&self.grid);
+        Ok(())
+    }
+
+    // The actions of the game with their key sequences, as the key map binds
+    // them. Only automata with several species paint them.
+    fn help_lines(&self) -> Vec<(String, String)> {
+        let species = self.grid.automaton().species();
+        Action::ALL
+            .into_iter()
+            .filter(|action| match action {
+                Action::Paint(painted) => species > 1 && *painted <= species,
+                _ => true,
+            })
+            .map(|action| {
+                let bindings = self.keymap.bindings(&action);
+                let keys = if bindings.is_empty() {
+                    "unbound".to_string()
+                } else {
+                    bindings.join(", ")
+                };
+                (action.description(), keys)
+            })
+            .chain([(
+                "Commands after :".to_string(),
+                command::COMMANDS
+                    .iter()
+                    .map(|(command, _)| *command)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )])
+            .collect()
+    }
+
+    // When the next generation is due, unless the game is paused or suspended.
+    // Jumps go on while the game is paused.
+    pub fn next_generation_at(&self) -> Option<Duration> {
+        if self.is_too_small() {
+            return None;
+        }
+        if self.jump_target.is_some() {
+            return Some(self.last_generation + JUMP_INTERVAL);
+        }
+        (!self.game_is_paused).then(|| self.last_generation + generation_interval(self.delay))
+    }
+
+    // Advance a generation when it is due. Generations keep to their schedule
+    // unless they are late by a whole interval.
+    pub fn tick(&mut self, now: Duration) -> Result<Vec<Draw>, Error> {
+        let Some(due) = self.next_generation_at().filter(|due| *due <= now) else {
+            return Ok(Vec::new());
+        };
+        if self.jump_target.is_some() {
+            let mut draws = self.jump(now)?;
+            // The help ribbon comes back when the jump is over
+            if self.jump_target.is_none() && self.message_is_shown {
+                self.message_is_shown = false;
+                draws.push(Draw::RibbonBottom);
+            }
+            return Ok(draws);
+        }
+        self.last_generation = if now - due < generation_interval(self.delay) {
+            due
+        } else {
+            now
+        };
+
+        self.advance()?;
+        Ok(vec![Draw::Cells, Draw::RibbonTop])
+    }
+
+    fn advance(&mut self) -> Result<(), Error> {
+        self.grid.next_generation();
+        self.statistics.record(&self.grid);
+
+        // Add the generation to the recording
+        if let Some(recorder) = &mut self.recorder {
+            recorder.add_frame(&self.grid, generation_interval(self.delay))?;
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, renderer: &mut impl Renderer, draws: &[Draw]) -> Result<(), Error> {
+        for draw in draws {
+            self.draw_one(renderer, draw).map_err(Error::terminal)?;
+        }
+        // The help stays over the cells drawn under it
+        let cells_are_drawn = draws
+            .iter()
+            .any(|draw| matches!(draw, Draw::Cells | Draw::Cell(_)));
+        if self.help_is_shown && cells_are_drawn && !draws.contains(&Draw::Help) {
+            self.draw_one(renderer, &Draw::Help)
+                .map_err(Error::terminal)?;
+        }
+        Ok(())
+    }
+
+    fn draw_one(
+        &self,
+        renderer: &mut impl Renderer,
+        draw: &Draw,
+    ) -> Result<(), game_of_life::Error> {
+        match draw {
+            Draw::Cells => {
+                print_cells(renderer, &self.grid)?;
+                if self.panel_is_visible {
+                    print_statistics(
+                        renderer,
+                        &self.statistics,
+                        &self.grid,
+                        &self.keymap,
+                        self.terminal_width,
+                    )?;
+                }
+            }
+            Draw::Cell(index) => print_cell(renderer, &self.grid, *index)?,
+            Draw::RibbonTop => print_ribbon_top(
+                renderer,
+                &self.grid,
+                self.delay,
+                self.recorder.is_some(),
+                self.species,
+            )?,
+            // The command line takes the place of the help ribbon
+            Draw::RibbonBottom if self.command_line.is_open() => {
+                print_message(renderer, self.terminal_height, &self.command_line.line())?
+            }
+            Draw::RibbonBottom => print_ribbon_bottom(
+                renderer,
+                &self.keymap,
+                (self.terminal_width, self.terminal_height),
+            )?,
+            Draw::Message(message) => print_message(renderer, self.terminal_height, message)?,
+            Draw::Help => print_help(
+                renderer,
+                &self.help_lines(),
+                (self.terminal_width, self.terminal_height),
+            )?,
+            Draw::TooSmall => print_too_small(
+                renderer,
+                (self.terminal_width, self.terminal_height),
+                (MIN_COLUMNS, MIN_ROWS),
+            )?,
+        }
+        Ok(())
+    }
+
+    // Finish the recording that is still in progress, and keep the game as a
+    // session that can be resumed, even when the recording fails
+    pub fn finish(self) -> (Session, Result<(), Error>) {
+        let finished = match self.recorder {
+            Some(recorder) => recorder.finish().map_err(Error::from),
+            None => Ok(()),
+        };
+        let session = Session {
+            grid: self.grid,
+            delay: self.delay,
+            game_is_paused: self.game_is_paused,
+        };
+        (session, finished)
+    }
+}
+
+pub fn generation_interval(delay: u8) -> Duration {
+    Duration::from_millis(8 * (delay as u64) + 250)
+}
+
+// How a seed is written after the arguments of :random
+fn seed_argument(seed: u64) -> String {
+    format!(" seed={seed}")
+}
+
+// Exports are saved in the working directory, named after the time they started
+fn export_path(extension: &str) -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    PathBuf::from(format!("game-of-life-{seconds}.{extension}"))
+}
+
+fn unknown_format(path: &Path) -> Error {
+    Error::Config(format!(
+        "{} is not an RLE (.rle) or Macrocell (.mc) file",
+        path.display()
+    ))
+}
@@ -0,0 +1,434 @@
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use game_of_life::automaton;
+use game_of_life::automaton::Automaton;
+use game_of_life::export::Region;
+use game_of_life::grid::DEAD;
+use game_of_life::render::Renderer;
+use game_of_life::rle;
+use game_of_life::rle::Rle;
+use game_of_life::Grid;
+
+use super::keymap::KeyMap;
+use super::App;
+use super::Input;
+use crate::exit::Error;
+
+// Inputs written to a text file, to play the game without a terminal in tests,
+// or to replay a game as it was played:
+//
+// size 80 24
+// rule B3/S23
+// keys pause = p, space
+// pattern x = 80, y = 22, rule = B3/S23
+// pattern 10$39b3o!
+// 0 key p
+// 1200 click 10 5
+// 2500 paste xq4_153
+// 4000 resize 100 30
+// 5000 key q
+//
+// The size of the screen, the rule, the lines of the key map and the lines of
+// the starting cells in the RLE format come first, then one input per line
+// after the milliseconds since the start of the game. Lines starting with # are
+// comments.
+pub struct Script {
+    pub columns: u16,
+    pub rows: u16,
+    pub rule: Option<Box<dyn Automaton>>,
+    pub keymap: Option<KeyMap>,
+    pub pattern: Option<Rle>,
+    pub inputs: Vec<(Duration, Input)>,
+}
+
+pub fn load(path: &Path) -> Result<Script, Error> {
+    fs::read_to_string(path)?.parse()
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut script = Script {
+            columns: 80,
+            rows: 24,
+            rule: None,
+            keymap: None,
+            pattern: None,
+            inputs: Vec::new(),
+        };
+        let (mut keymap, mut pattern) = (Vec::new(), Vec::new());
+        for (line_number, line) in (1..).zip(text.lines()) {
+            let invalid =
+                || invalid_data(format!("invalid line {line_number} of the script: {line}"));
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(size) = line.strip_prefix("size ") {
+                let (columns, rows) = size.trim().split_once(' ').ok_or_else(invalid)?;
+                script.columns = columns.parse().map_err(|_| invalid())?;
+                script.rows = rows.trim().parse().map_err(|_| invalid())?;
+            } else if let Some(rule) = line.strip_prefix("rule ") {
+                script.rule = Some(automaton::parse(rule.trim())?);
+            } else if let Some(line) = line.strip_prefix("keys ") {
+                keymap.push(line);
+            } else if let Some(line) = line.strip_prefix("pattern ") {
+                pattern.push(line);
+            } else {
+                let (milliseconds, input) = line.split_once(' ').ok_or_else(invalid)?;
+                let time = Duration::from_millis(milliseconds.parse().map_err(|_| invalid())?);
+                if script.inputs.last().is_some_and(|(last, _)| *last > time) {
+                    return Err(invalid_data(format!(
+                        "line {line_number} of the script goes back in time"
+                    )));
+                }
+                script
+                    .inputs
+                    .push((time, input.parse().map_err(|_| invalid())?));
+            }
+        }
+        if !keymap.is_empty() {
+            script.keymap = Some(keymap.join("\n").parse()?);
+        }
+        if !pattern.is_empty() {
+            script.pattern = Some(pattern.join("\n").parse()?);
+        }
+        Ok(script)
+    }
+}
+
+impl Display for Input {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Key(key) => write!(formatter, "key {key}"),
+            Input::Interrupt => write!(formatter, "interrupt"),
+            Input::Click { column, row } => write!(formatter, "click {column} {row}"),
+            // Scripts have one input per line
+            Input::Paste(text) => write!(formatter, "paste {}", text.replace(['\r', '\n'], " ")),
+            Input::Resize { columns, rows } => write!(formatter, "resize {columns} {rows}"),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, arguments) = text.split_once(' ').unwrap_or((text, ""));
+        let numbers = || -> Result<(u16, u16), ()> {
+            let (first, second) = arguments.trim().split_once(' ').ok_or(())?;
+            Ok((
+                first.parse().map_err(|_| ())?,
+                second.trim().parse().map_err(|_| ())?,
+            ))
+        };
+        match name {
+            "key" => arguments.parse().map(Input::Key),
+            "interrupt" if arguments.is_empty() => Ok(Input::Interrupt),
+            "click" => numbers().map(|(column, row)| Input::Click { column, row }),
+            "paste" => Ok(Input::Paste(arguments.to_string())),
+            "resize" => numbers().map(|(columns, rows)| Input::Resize { columns, rows }),
+            _ => Err(()),
+        }
+    }
+}
+
+// Writes the inputs of a game to a script as they come
+pub struct ScriptRecorder {
+    file: BufWriter<File>,
+}
+
+impl ScriptRecorder {
+    // The game starts from the grid with the key map, which the script keeps
+    // so that it replays the same way anywhere
+    pub fn create(
+        path: &Path,
+        (columns, rows): (u16, u16),
+        grid: &Grid,
+        keymap: &KeyMap,
+    ) -> Result<Self, Error> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "size {columns} {rows}")?;
+        writeln!(file, "rule {}", grid.automaton().name())?;
+        if *keymap != KeyMap::default() {
+            for line in keymap.to_string().lines() {
+                writeln!(file, "keys {line}")?;
+            }
+        }
+        let is_empty =
+            (0..grid.height).all(|row| (0..grid.width).all(|column| grid[(column, row)] == DEAD));
+        if !is_empty {
+            let pattern = rle::write(grid, &Region::whole(grid))?;
+            for line in pattern.lines().filter(|line| !line.starts_with('#')) {
+                writeln!(file, "pattern {line}")?;
+            }
+        }
+        Ok(ScriptRecorder { file })
+    }
+
+    pub fn record(&mut self, time: Duration, input: &Input) -> Result<(), io::Error> {
+        writeln!(self.file, "{} {input}", time.as_millis())
+    }
+
+    pub fn finish(mut self) -> Result<(), io::Error> {
+        self.file.flush()
+    }
+}
+
+// Play the inputs of a script, with the generations that are due before each
+// input. `wait_until` is called before each of them with its time, and stops
+// the script by returning false: tests run scripts at once, the terminal waits
+// for the time to come.
+pub fn play(
+    inputs: &[(Duration, Input)],
+    app: &mut App,
+    renderer: &mut impl Renderer,
+    mut wait_until: impl FnMut(Duration) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    app.draw(renderer, &app.start())?;
+    for (time, input) in inputs {
+        while let Some(due) = app.next_generation_at().filter(|due| due <= time) {
+            if !wait_until(due)? {
+                return Ok(());
+            }
+            let draws = app.tick(due)?;
+            app.draw(renderer, &draws)?;
+        }
+        if !wait_until(*time)? {
+            return Ok(());
+        }
+        if let Input::Resize { columns, rows } = *input {
+            renderer.resize(columns, rows).map_err(Error::terminal)?;
+        }
+        let draws = app.handle(input.clone(), *time)?;
+        app.draw(renderer, &draws)?;
+        if app.has_quit() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::Pattern(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::keymap::Key;
+    use crate::app::VERTICAL_MARGIN;
+    use game_of_life::render::Memory;
+    use std::env;
+    use std::process;
+
+    fn play_script(text: &str) -> (App, Memory) {
+        let script: Script = text.parse().unwrap();
+        let mut grid = Grid::new(script.columns, script.rows - VERTICAL_MARGIN);
+        if let Some(rule) = script.rule {
+            grid.set_automaton(rule);
+        }
+        let mut app = App::new(grid, 50, true, (script.columns, script.rows));
+        let mut screen = Memory::new(script.columns, script.rows);
+        play(&script.inputs, &mut app, &mut screen, |_| Ok(true)).unwrap();
+        (app, screen)
+    }
+
+    fn cells(grid: &Grid) -> Vec<u8> {
+        (0..grid.height)
+            .flat_map(|height| (0..grid.width).map(move |width| grid[(width, height)]))
+            .collect()
+    }
+
+    #[test]
+    fn scripts_play_the_game() {
+        // A blinker drawn with clicks, then two generations at speed 50, which
+        // are 650 milliseconds apart
+        let (app, screen) = play_script(
+            "size 80 8\n\
+             rule B3/S23\n\
+             0 click 4 4\n\
+             10 click 5 4\n\
+             20 click 6 4\n\
+             30 key p\n\
+             # Clicks only edit the paused game\n\
+             700 click 9 6\n\
+             1400 key p\n\
+             1500 click 1 2\n\
+             1600 key q\n",
+        );
+
+        assert_eq!(app.grid.generation, 2);
+        assert!(app.has_quit());
+        assert_eq!(
+            screen.text(),
+            [
+                "Generation: 2             Speed: 50         Rule: B3/S23",
+                "Population: 4",
+                " #",
+                "",
+                "    ###",
+                "",
+                "",
+                "q: quit    p: pause    speed: +-    r: record    s: snapshot    ?: help",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn inputs_at_the_due_time_come_after_the_generation() {
+        // Generations at speed 50 are 650 milliseconds apart, the pause at the
+        // time of the first one lets it happen
+        let (app, _) = play_script(
+            "size 40 10\n\
+             0 click 4 4\n\
+             0 click 5 4\n\
+             0 click 6 4\n\
+             0 key p\n\
+             650 key p\n\
+             5000 key q\n",
+        );
+
+        assert_eq!(app.grid.generation, 1);
+        assert!(app.game_is_paused);
+    }
+
+    #[test]
+    fn small_terminals_suspend_the_game() {
+        // The blinker would turn 3 times while the terminal is too small
+        let (app, screen) = play_script(
+            "size 30 10\n\
+             0 click 4 4\n\
+             0 click 5 4\n\
+             0 click 6 4\n\
+             0 key p\n\
+             10 resize 30 3\n\
+             20 key p\n\
+             2000 resize 30 10\n\
+             2100 key q\n",
+        );
+
+        assert_eq!(app.grid.generation, 0);
+        assert_eq!(app.grid.population, 3);
+        assert_eq!(screen.line(4), "    ###");
+
+        let (_, screen) = play_script("size 24 3\n0 click 4 4\n");
+        assert_eq!(screen.text(), "   Terminal too small\n       need 20×8\n");
+    }
+
+    #[test]
+    fn commands_are_typed_after_a_colon() {
+        let (app, screen) = play_script(
+            "size 40 10\n\
+             0 click 4 4\n\
+             0 click 5 4\n\
+             0 click 6 4\n\
+             0 key :\n\
+             0 paste step 3\n\
+             10 key enter\n\
+             20 key :\n\
+             20 paste rule B36/S23\n\
+             30 key enter\n\
+             40 key :\n\
+             40 key g\n\
+             40 key o\n\
+             40 key tab\n\
+             40 key 1\n\
+             50 key enter\n",
+        );
+
+        assert_eq!(app.grid.generation, 3);
+        assert_eq!(app.grid.automaton().name(), "B36/S23");
+        assert_eq!(screen.line(3), "     #");
+        assert_eq!(screen.line(5), "     #");
+        assert_eq!(screen.line(9), ":goto 1: Generation 1 has already passed");
+    }
+
+    #[test]
+    fn keys_stop_long_jumps() {
+        // A chunk of generations when the command is entered, and another
+        // 10 milliseconds later, before the key stops the jump
+        let (app, screen) = play_script(
+            "size 40 10\n\
+             0 key :\n\
+             0 paste step 100000000\n\
+             10 key enter\n\
+             25 key x\n\
+             # The jump is over by the next key\n\
+             1000 key :\n",
+        );
+
+        assert_eq!(app.grid.generation, 512);
+        assert_eq!(app.next_generation_at(), None);
+        assert_eq!(screen.line(0).split_whitespace().nth(1), Some("512"));
+    }
+
+    #[test]
+    fn scripts_start_from_the_recorded_game() {
+        let path = env::temp_dir().join(format!("game-of-life-test-{}.script", process::id()));
+        let mut grid = Grid::new(40, 8);
+        for cell in [(3, 4), (4, 4), (5, 4), (30, 1)] {
+            grid.cycle_cell(cell);
+        }
+        let keymap: KeyMap = "pause = space\nquit =".parse().unwrap();
+        ScriptRecorder::create(&path, (40, 10), &grid, &keymap)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let script = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            script.keymap.map(|keymap| keymap.to_string()),
+            Some(keymap.to_string())
+        );
+        let mut replayed = Grid::new(40, 8);
+        script.pattern.unwrap().place(&mut replayed);
+        assert_eq!(cells(&replayed), cells(&grid));
+    }
+
+    #[test]
+    fn random_seeds_are_typed_into_the_script() {
+        let text = "size 40 10\n0 key :\n0 paste random 0.5\n10 key enter\n";
+        let (mut app, _) = play_script(text);
+        let seed = app.take_drawn_seed().unwrap();
+        assert_eq!(app.take_drawn_seed(), None);
+
+        let replayed =
+            format!("size 40 10\n0 key :\n0 paste random 0.5\n10 {seed}\n10 key enter\n");
+        let (mut replayed_app, _) = play_script(&replayed);
+        assert_eq!(replayed_app.take_drawn_seed(), None);
+        assert_eq!(cells(&replayed_app.grid), cells(&app.grid));
+    }
+
+    #[test]
+    fn inputs_round_trip() {
+        let inputs = [
+            Input::Key(Key::from('+')),
+            Input::Key(Key::from(' ')),
+            Input::Key("ctrl+alt+up".parse().unwrap()),
+            Input::Interrupt,
+            Input::Click { column: 3, row: 4 },
+            Input::Paste("xq4_153".to_string()),
+            Input::Resize {
+                columns: 100,
+                rows: 30,
+            },
+        ];
+        for input in inputs {
+            assert_eq!(input.to_string().parse(), Ok(input));
+        }
+        assert!("10 key".parse::<Script>().is_err());
+        assert!("20 key p\n10 key p".parse::<Script>().is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use super::Automaton;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::color::CELL_COLOR;
+use crate::error::Error;
+use crate::grid::Grid;
+
+const ANT_COLOR: Color = Color::Red;
+
+// The directions an ant faces, as saved in sessions
+const DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+// Colors of the cells after the first two, repeated when there are more colors
+const PALETTE: [Color; 6] = [
+    Color::Green,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::DarkYellow,
+    Color::Grey,
+];
+
+/// Langton's ant, and the turmites that generalize it to more colors: an ant
+/// turns as told by the color of its cell, moves the cell to the next color and
+/// steps forward. "RL" is Langton's ant: turn right on color 0, left on color 1.
+/// The ant starts in the middle of the grid and stops when it walks off an edge.
+/// <https://conwaylife.com/wiki/Langton%27s_ant>
+pub struct Ant {
+    // The turn for each color, in quarter turns clockwise
+    turns: Vec<u8>,
+    position: Option<(u16, u16)>,
+    // 0 for north, 1 for east, 2 for south and 3 for west
+    direction: u8,
+}
+
+impl Ant {
+    /// Turns are written R (right), L (left), N (none) and U (u-turn)
+    pub fn new(turns: &str) -> Result<Self, Error> {
+        let turns = turns
+            .chars()
+            .map(|turn| match turn.to_ascii_uppercase() {
+                'N' => Some(0),
+                'R' => Some(1),
+                'U' => Some(2),
+                'L' => Some(3),
+                _ => None,
+            })
+            .collect::<Option<Vec<u8>>>()
+            .filter(|turns| (2..=u8::MAX as usize).contains(&turns.len()))
+            .ok_or_else(|| {
+                Error::InvalidRule(format!(
+                    "invalid ant \"{turns}\": expected 2 to 255 of R, L, N and U"
+                ))
+            })?;
+        Ok(Ant {
+            turns,
+            position: None,
+            direction: 0,
+        })
+    }
+}
+
+impl Automaton for Ant {
+    fn name(&self) -> String {
+        let turns: String = self
+            .turns
+            .iter()
+            .map(|turn| ['N', 'R', 'U', 'L'][*turn as usize])
+            .collect();
+        format!("Ant:{turns}")
+    }
+
+    fn states(&self) -> u8 {
+        self.turns.len() as u8
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let Some((width, height)) = self.position else {
+            return Vec::new();
+        };
+        let state = grid[(width, height)];
+        self.direction = (self.direction + self.turns[state as usize]) % 4;
+
+        let (column_step, row_step) = [(0, -1), (1, 0), (0, 1), (-1, 0)][self.direction as usize];
+        let column = width.checked_add_signed(column_step);
+        let row = height.checked_add_signed(row_step);
+        self.position = column
+            .zip(row)
+            .filter(|(column, row)| *column < grid.width && *row < grid.height);
+
+        vec![((width, height), (state + 1) % self.states())]
+    }
+
+    fn color(&self, index: (u16, u16), state: u8) -> Color {
+        if self.position == Some(index) {
+            return ANT_COLOR;
+        }
+        match state {
+            0 => BACKGROUND_COLOR,
+            1 => CELL_COLOR,
+            _ => PALETTE[(state as usize - 2) % PALETTE.len()],
+        }
+    }
+
+    // Where the ant is and where it is going, as "COLUMN,ROW DIRECTION"
+    fn saved_state(&self) -> Option<String> {
+        let (width, height) = self.position?;
+        Some(format!(
+            "{width},{height} {}",
+            DIRECTIONS[self.direction as usize]
+        ))
+    }
+
+    fn restore_state(&mut self, state: &str) -> Result<(), Error> {
+        let invalid = || Error::InvalidRule(format!("invalid ant state \"{state}\""));
+        let (position, direction) = state.split_once(' ').ok_or_else(invalid)?;
+        let (width, height) = position.split_once(',').ok_or_else(invalid)?;
+        let position = (
+            width.parse().map_err(|_| invalid())?,
+            height.parse().map_err(|_| invalid())?,
+        );
+        let direction = DIRECTIONS
+            .iter()
+            .position(|name| *name == direction)
+            .ok_or_else(invalid)?;
+        self.position = Some(position);
+        self.direction = direction as u8;
+        Ok(())
+    }
+
+    // An ant that walked off the grid, or that the grid shrank away from,
+    // starts again from the middle
+    fn fit(&mut self, width: u16, height: u16) {
+        let is_inside = self
+            .position
+            .is_some_and(|(column, row)| column < width && row < height);
+        if !is_inside && width > 0 && height > 0 {
+            self.position = Some((width / 2, height / 2));
+            self.direction = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn langtons_ant_turns_by_color() {
+        let mut grid = Grid::new(5, 5);
+        grid.set_automaton(Box::new(Ant::new("RL").unwrap()));
+
+        // Four right turns on empty cells bring the ant back where it started
+        for _ in 0..4 {
+            grid.next_generation();
+        }
+        assert_eq!(grid.population, 4);
+        for cell_index in [(2, 2), (3, 2), (3, 3), (2, 3)] {
+            assert_eq!(grid[cell_index], 1);
+        }
+
+        // The ant turns left on its colored starting cell and clears it
+        grid.next_generation();
+        assert_eq!(grid[(2, 2)], 0);
+        assert_eq!(grid.automaton().name(), "Ant:RL");
+    }
+
+    #[test]
+    fn invalid_turns_are_rejected() {
+        assert!(Ant::new("RX").is_err());
+        assert!(Ant::new("R").is_err());
+    }
+}
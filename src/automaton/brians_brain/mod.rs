@@ -0,0 +1,81 @@
+use super::Automaton;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::grid::Grid;
+
+/// Firing cells start dying in the next generation and are off in the one after,
+/// off cells fire when exactly two of their neighbors are firing.
+/// <https://conwaylife.com/wiki/Brian%27s_Brain>
+pub struct BriansBrain;
+
+const OFF: u8 = 0;
+const FIRING: u8 = 1;
+const DYING: u8 = 2;
+
+impl Automaton for BriansBrain {
+    fn name(&self) -> String {
+        "BriansBrain".to_string()
+    }
+
+    fn states(&self) -> u8 {
+        3
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let mut changed_cells = Vec::new();
+        for width in 0..grid.width {
+            for height in 0..grid.height {
+                let index = (width, height);
+                let next_state = match grid[index] {
+                    FIRING => DYING,
+                    DYING => OFF,
+                    _ if grid.neighborhood(index).count_ones() == 2 => FIRING,
+                    _ => continue,
+                };
+                changed_cells.push((index, next_state));
+            }
+        }
+        changed_cells
+    }
+
+    fn color(&self, _index: (u16, u16), state: u8) -> Color {
+        match state {
+            FIRING => Color::White,
+            DYING => Color::DarkBlue,
+            _ => BACKGROUND_COLOR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton;
+
+    // Brian's Brain is the Generations rule where nothing survives, two
+    // neighbors give birth, and cells take one generation to die
+    #[test]
+    fn plays_like_its_generations_rule() {
+        let mut brain = Grid::new(12, 12);
+        brain.set_automaton(Box::new(BriansBrain));
+        let mut generations = Grid::new(12, 12);
+        generations.set_automaton(automaton::parse("/2/3").unwrap());
+        for index in [(4, 4), (5, 4), (5, 5), (7, 6), (7, 7), (2, 8)] {
+            brain.cycle_cell(index);
+            generations.cycle_cell(index);
+        }
+
+        for _ in 0..4 {
+            brain.next_generation();
+            generations.next_generation();
+            let cells = |grid: &Grid| {
+                (0..12)
+                    .flat_map(|height| (0..12).map(move |width| (width, height)))
+                    .map(|index| grid[index])
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(cells(&brain), cells(&generations));
+        }
+        assert!(brain.population > 0);
+    }
+}
@@ -0,0 +1,126 @@
+use super::Automaton;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::color::CELL_COLOR;
+use crate::grid::Grid;
+use crate::grid::ALIVE;
+use crate::grid::DEAD;
+use crate::rule::Neighborhood;
+
+// The colors of the species, the first one being the usual cell color
+const SPECIES_COLORS: [Color; 4] = [CELL_COLOR, Color::Red, Color::Green, Color::Cyan];
+
+/// Life with colored cells: Immigration has two species and QuadLife four. Cells
+/// live and die as in Life whatever their color, and a cell that is born takes
+/// the color most of its three parents have. In QuadLife, a cell whose three
+/// parents all differ takes the fourth color.
+/// <https://conwaylife.com/wiki/Colourised_Life>
+pub struct Colored {
+    species: u8,
+}
+
+impl Colored {
+    pub fn immigration() -> Self {
+        Colored { species: 2 }
+    }
+
+    pub fn quad_life() -> Self {
+        Colored { species: 4 }
+    }
+
+    // The color of a cell born to parents of the given colors
+    fn birth_color(&self, parents: &[u8]) -> u8 {
+        let mut counts = [0; SPECIES_COLORS.len()];
+        for parent in parents {
+            counts[(parent - ALIVE) as usize] += 1;
+        }
+        let most = counts.iter().max().copied().unwrap_or_default();
+        let colors = ALIVE..=self.species;
+        match counts.iter().filter(|count| **count == most).count() {
+            1 => colors.zip(counts).find(|(_, count)| *count == most),
+            _ => colors.zip(counts).find(|(_, count)| *count == 0),
+        }
+        .map_or(ALIVE, |(color, _)| color)
+    }
+}
+
+impl Automaton for Colored {
+    fn name(&self) -> String {
+        match self.species {
+            2 => "Immigration".to_string(),
+            _ => "QuadLife".to_string(),
+        }
+    }
+
+    fn states(&self) -> u8 {
+        self.species + 1
+    }
+
+    fn species(&self) -> u8 {
+        self.species
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let mut changed_cells = Vec::new();
+        let offsets = Neighborhood::Moore.offsets(0);
+        for width in 0..grid.width {
+            for height in 0..grid.height {
+                let index = (width, height);
+                let parents: Vec<u8> = offsets
+                    .iter()
+                    .filter_map(|(column_offset, row_offset)| {
+                        let offset = (*column_offset as isize, *row_offset as isize);
+                        grid.neighbor(index, offset)
+                    })
+                    .filter(|state| *state != DEAD)
+                    .collect();
+
+                let state = grid[index];
+                match (state, parents.len()) {
+                    (DEAD, 3) => changed_cells.push((index, self.birth_color(&parents))),
+                    (DEAD, _) | (_, 2 | 3) => {}
+                    _ => changed_cells.push((index, DEAD)),
+                }
+            }
+        }
+        changed_cells
+    }
+
+    fn color(&self, _index: (u16, u16), state: u8) -> Color {
+        match state {
+            DEAD => BACKGROUND_COLOR,
+            _ => SPECIES_COLORS[(state - ALIVE) as usize % SPECIES_COLORS.len()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn births_take_the_color_of_most_parents() {
+        // A blinker with two cells of the first species and one of the second
+        let mut grid = Grid::new(5, 5);
+        grid.set_automaton(Box::new(Colored::immigration()));
+        grid.set_cell((2, 1), ALIVE);
+        grid.set_cell((2, 2), 2);
+        grid.set_cell((2, 3), ALIVE);
+        assert_eq!(grid.population, 3);
+
+        grid.next_generation();
+        assert_eq!(
+            [grid[(1, 2)], grid[(2, 2)], grid[(3, 2)]],
+            [ALIVE, 2, ALIVE]
+        );
+        assert_eq!(grid.species_populations(), vec![2, 1]);
+    }
+
+    #[test]
+    fn quad_life_parents_of_three_colors_give_the_fourth() {
+        let colored = Colored::quad_life();
+        assert_eq!(colored.birth_color(&[1, 2, 3]), 4);
+        assert_eq!(colored.birth_color(&[4, 2, 4]), 4);
+        assert_eq!(colored.birth_color(&[2, 4, 1]), 3);
+    }
+}
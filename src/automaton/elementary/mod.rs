@@ -0,0 +1,220 @@
+use std::str::FromStr;
+
+use super::Automaton;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::color::CELL_COLOR;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::grid::ALIVE;
+use crate::grid::DEAD;
+use crate::random::Random;
+
+/// One of Wolfram's 256 elementary automata, running on the top row of the grid.
+/// Each generation the rows below scroll down, so the grid shows the history of
+/// the row with the most recent generations at the top.
+///
+/// A cell's next state is the bit of the rule number given by the cell and its
+/// left and right neighbors, read as a 3 bit number (left being the highest).
+/// Cells beyond the edges are dead, or the edges wrap around with ":T", as in
+/// Golly's torus suffix: W30, W110:T.
+/// <https://mathworld.wolfram.com/ElementaryCellularAutomaton.html>
+pub struct Elementary {
+    rule: u8,
+    wraps: bool,
+}
+
+impl Elementary {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = || {
+            Error::InvalidRule(format!(
+                "invalid elementary rule \"{text}\": expected W0 to W255, then :T to wrap"
+            ))
+        };
+        let number = text.strip_prefix(['W', 'w']).ok_or_else(invalid)?;
+        let (number, wraps) = match number.split_once(':') {
+            Some((number, "T" | "t")) => (number, true),
+            Some(_) => return Err(invalid()),
+            None => (number, false),
+        };
+        Ok(Elementary {
+            rule: number.parse().map_err(|_| invalid())?,
+            wraps,
+        })
+    }
+
+    pub fn is_rule_string(text: &str) -> bool {
+        let mut characters = text.chars();
+        matches!(characters.next(), Some('W' | 'w'))
+            && characters
+                .next()
+                .is_some_and(|digit| digit.is_ascii_digit())
+    }
+}
+
+impl Automaton for Elementary {
+    fn name(&self) -> String {
+        let suffix = if self.wraps { ":T" } else { "" };
+        format!("W{}{suffix}", self.rule)
+    }
+
+    fn states(&self) -> u8 {
+        2
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let mut changed_cells = Vec::new();
+        if grid.width == 0 {
+            return changed_cells;
+        }
+
+        let is_alive = |width: Option<u16>| width.is_some_and(|width| grid[(width, 0)] == ALIVE);
+        for width in 0..grid.width {
+            let (left, right) = if self.wraps {
+                let left = width.checked_sub(1).unwrap_or(grid.width - 1);
+                (Some(left), Some((width + 1) % grid.width))
+            } else {
+                (
+                    width.checked_sub(1),
+                    Some(width + 1).filter(|right| *right < grid.width),
+                )
+            };
+            let pattern = (is_alive(left) as u8) << 2
+                | (is_alive(Some(width)) as u8) << 1
+                | is_alive(right) as u8;
+            let next_state = if self.rule >> pattern & 1 == 1 {
+                ALIVE
+            } else {
+                DEAD
+            };
+            if next_state != grid[(width, 0)] {
+                changed_cells.push(((width, 0), next_state));
+            }
+
+            // The history scrolls down
+            for height in 1..grid.height {
+                let state = grid[(width, height - 1)];
+                if state != grid[(width, height)] {
+                    changed_cells.push(((width, height), state));
+                }
+            }
+        }
+        changed_cells
+    }
+
+    fn color(&self, _index: (u16, u16), state: u8) -> Color {
+        if state == ALIVE {
+            CELL_COLOR
+        } else {
+            BACKGROUND_COLOR
+        }
+    }
+}
+
+/// What the top row starts as: a single live cell in the middle, random cells,
+/// or a row of cells given as text ('O' or '1' for live cells), in the middle
+pub enum InitialRow {
+    /// One live cell in the middle
+    Single,
+    /// Cells alive at random, one in two
+    Random,
+    /// Cells given from left to right, true for live cells
+    Cells(Vec<bool>),
+}
+
+impl InitialRow {
+    /// Put the cells on the top row of the grid, centered
+    pub fn apply(&self, grid: &mut Grid) {
+        if grid.height == 0 {
+            return;
+        }
+        let cells = match self {
+            InitialRow::Single => vec![true],
+            InitialRow::Random => {
+                let mut random = Random::new(Random::time_seed());
+                (0..grid.width).map(|_| random.chance(0.5)).collect()
+            }
+            InitialRow::Cells(cells) => cells.clone(),
+        };
+
+        let left = (grid.width as isize - cells.len() as isize) / 2;
+        for (offset, is_alive) in cells.into_iter().enumerate() {
+            let width = left + offset as isize;
+            if (0..grid.width as isize).contains(&width) {
+                grid.set_cell((width as u16, 0), if is_alive { ALIVE } else { DEAD });
+            }
+        }
+    }
+}
+
+impl FromStr for InitialRow {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "single" => Ok(InitialRow::Single),
+            "random" => Ok(InitialRow::Random),
+            _ => text
+                .chars()
+                .map(|cell| match cell {
+                    'O' | 'o' | '1' | '*' => Some(true),
+                    '.' | '0' => Some(false),
+                    _ => None,
+                })
+                .collect::<Option<Vec<bool>>>()
+                .map(InitialRow::Cells)
+                .ok_or_else(|| {
+                    Error::InvalidPattern(format!("invalid initial row \"{text}\": expected single, random or cells like .O.O"))
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rule: &str, initial_row: &str) -> Grid {
+        let mut grid = Grid::new(7, 3);
+        grid.set_automaton(Box::new(Elementary::parse(rule).unwrap()));
+        initial_row.parse::<InitialRow>().unwrap().apply(&mut grid);
+        grid
+    }
+
+    fn row(grid: &Grid, height: u16) -> String {
+        (0..grid.width)
+            .map(|width| {
+                if grid[(width, height)] == ALIVE {
+                    'O'
+                } else {
+                    '.'
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn history_scrolls_down() {
+        let mut grid = grid("W90", "single");
+        grid.next_generation();
+        grid.next_generation();
+
+        assert_eq!(row(&grid, 0), ".O...O.");
+        assert_eq!(row(&grid, 1), "..O.O..");
+        assert_eq!(row(&grid, 2), "...O...");
+    }
+
+    #[test]
+    fn edges_are_fixed_or_wrap() {
+        // Rule 2 moves every cell one step to the left
+        let mut fixed = grid("W2", "O......");
+        let mut wrapping = grid("W2:T", "O......");
+        fixed.next_generation();
+        wrapping.next_generation();
+
+        assert_eq!(row(&fixed, 0), ".......");
+        assert_eq!(row(&wrapping, 0), "......O");
+        assert_eq!(wrapping.automaton().name(), "W2:T");
+        assert!(Elementary::parse("W256").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use super::Automaton;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::color::CELL_COLOR;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::grid::ALIVE;
+use crate::grid::DEAD;
+
+// Named block rules, as MCell and Golly write them
+const NAMED_RULES: [(&str, &str); 3] = [
+    ("critters", "M/15,14,13,3,11,5,6,1,7,9,10,2,12,4,8,0"),
+    ("tron", "M/15,1,2,3,4,5,6,7,8,9,10,11,12,13,14,0"),
+    (
+        "billiardballmodel",
+        "M/0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,15",
+    ),
+];
+
+/// A partitioning automaton on the Margolus neighborhood: the grid is cut into
+/// 2x2 blocks, and each block is replaced as a whole through a lookup table.
+/// The blocks start at even cells in even generations and at odd cells in odd
+/// generations, so information crosses block boundaries. Blocks cut by the
+/// edges of the grid don't change.
+///
+/// A block is numbered by its live cells, 1 for the top left, 2 for the top
+/// right, 4 for the bottom left and 8 for the bottom right, and the rule lists
+/// what each of the 16 blocks becomes: M/0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,15
+/// <https://conwaylife.com/wiki/Block_cellular_automaton>
+pub struct Margolus {
+    table: [u8; 16],
+}
+
+impl Margolus {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = || {
+            Error::InvalidRule(format!(
+                "invalid block rule \"{text}\": expected M/ and 16 blocks from 0 to 15"
+            ))
+        };
+        let blocks = text.strip_prefix(['M', 'm']).ok_or_else(invalid)?;
+        let blocks = blocks.strip_prefix('/').ok_or_else(invalid)?;
+        let blocks = blocks
+            .split(',')
+            .map(|block| block.trim().parse::<u8>().ok().filter(|block| *block < 16))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        Ok(Margolus {
+            table: blocks.try_into().map_err(|_| invalid())?,
+        })
+    }
+
+    /// A block rule by name (Critters, Tron, BilliardBallModel), the name in
+    /// lowercase without spaces
+    pub fn named(name: &str) -> Option<Self> {
+        NAMED_RULES
+            .iter()
+            .find(|(rule_name, _)| *rule_name == name)
+            .and_then(|(_, rule)| Margolus::parse(rule).ok())
+    }
+
+    pub fn is_rule_string(text: &str) -> bool {
+        let mut characters = text.chars();
+        matches!(characters.next(), Some('M' | 'm')) && characters.next() == Some('/')
+    }
+}
+
+impl Automaton for Margolus {
+    fn name(&self) -> String {
+        let blocks: Vec<String> = self.table.iter().map(u8::to_string).collect();
+        format!("M/{}", blocks.join(","))
+    }
+
+    fn states(&self) -> u8 {
+        2
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let mut changed_cells = Vec::new();
+        let offset = (grid.generation % 2) as u16;
+
+        for top in (offset..grid.height.saturating_sub(1)).step_by(2) {
+            for left in (offset..grid.width.saturating_sub(1)).step_by(2) {
+                let cells = [
+                    (left, top),
+                    (left + 1, top),
+                    (left, top + 1),
+                    (left + 1, top + 1),
+                ];
+                let block = cells
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, index)| grid[**index] == ALIVE)
+                    .fold(0, |block, (bit, _)| block | 1 << bit);
+
+                let next_block = self.table[block];
+                for (bit, index) in cells.into_iter().enumerate() {
+                    let next_state = if next_block >> bit & 1 == 1 {
+                        ALIVE
+                    } else {
+                        DEAD
+                    };
+                    if next_state != grid[index] {
+                        changed_cells.push((index, next_state));
+                    }
+                }
+            }
+        }
+        changed_cells
+    }
+
+    fn color(&self, _index: (u16, u16), state: u8) -> Color {
+        if state == ALIVE {
+            CELL_COLOR
+        } else {
+            BACKGROUND_COLOR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn billiard_balls_move_diagonally() {
+        let mut grid = Grid::new(6, 6);
+        grid.set_automaton(Box::new(Margolus::named("billiardballmodel").unwrap()));
+        grid.cycle_cell((0, 0));
+
+        // The blocks alternate between even and odd offsets
+        grid.next_generation();
+        assert_eq!(grid[(1, 1)], ALIVE);
+        grid.next_generation();
+        assert_eq!(grid[(2, 2)], ALIVE);
+        assert_eq!(grid.population, 1);
+    }
+
+    #[test]
+    fn rules_round_trip() {
+        let rule = NAMED_RULES[0].1;
+        assert_eq!(Margolus::parse(rule).unwrap().name(), rule);
+        for text in ["M/0,1,2", "M/0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,16", "M0,1"] {
+            assert!(Margolus::parse(text).is_err(), "{text} should be invalid");
+        }
+    }
+}
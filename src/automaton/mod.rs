@@ -0,0 +1,112 @@
+mod ant;
+mod brians_brain;
+mod colored;
+mod elementary;
+mod margolus;
+mod stochastic;
+mod wireworld;
+
+use ant::Ant;
+use brians_brain::BriansBrain;
+use colored::Colored;
+use elementary::Elementary;
+pub use elementary::InitialRow;
+use margolus::Margolus;
+use stochastic::Stochastic;
+use wireworld::WireWorld;
+
+use crate::color::Color;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::rule::Rule;
+
+/// What makes the cells of a grid change from one generation to the next.
+/// Life-like rules are one kind of automaton, others that are not Life-like at
+/// all (WireWorld, Langton's ant...) plug into the grid the same way.
+pub trait Automaton {
+    /// The rule string, shown in the ribbon and saved in sessions
+    fn name(&self) -> String;
+
+    /// Cells are in states from 0 to `states() - 1`, 0 being the empty state
+    fn states(&self) -> u8;
+
+    /// The cells that change in the next generation, with their new state
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)>;
+
+    /// The color of a cell in a state
+    fn color(&self, index: (u16, u16), state: u8) -> Color;
+
+    /// The number of species of live cells, in the states from ALIVE up. The
+    /// ribbon counts each species, and clicks paint the species picked.
+    fn species(&self) -> u8 {
+        1
+    }
+
+    /// The state a cell goes to when it is clicked
+    fn clicked_state(&self, state: u8) -> u8 {
+        (state + 1) % self.states()
+    }
+
+    /// Called with the size of the grid when the automaton is set and when the
+    /// grid is resized
+    fn fit(&mut self, _width: u16, _height: u16) {}
+
+    /// Whether odd rows are drawn shifted by half a cell
+    fn is_staggered(&self) -> bool {
+        false
+    }
+
+    /// The seed of the random numbers of automata that use them
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// What the automaton remembers besides the cells, like where an ant is,
+    /// so sessions can save it
+    fn saved_state(&self) -> Option<String> {
+        None
+    }
+
+    /// Bring back the state `saved_state` gave
+    fn restore_state(&mut self, _state: &str) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Parse an automaton by name (WireWorld, BriansBrain, LangtonsAnt, Immigration,
+/// QuadLife, Critters, Tron, BilliardBallModel), a turmite as Ant: and its turns (Ant:RLR), an
+/// elementary automaton (W110), a block rule (M/...), or a Life-like rule that
+/// may be followed by the settings of a stochastic rule (B3/S23;birth=0.95).
+pub fn parse(text: &str) -> Result<Box<dyn Automaton>, Error> {
+    if text.contains(';') {
+        return Ok(Box::new(Stochastic::parse(text.trim())?));
+    }
+    if Elementary::is_rule_string(text.trim()) {
+        return Ok(Box::new(Elementary::parse(text.trim())?));
+    }
+    if Margolus::is_rule_string(text.trim()) {
+        return Ok(Box::new(Margolus::parse(text.trim())?));
+    }
+    if let Some((prefix, turns)) = text.trim().split_once(':') {
+        if prefix.eq_ignore_ascii_case("ant") {
+            return Ok(Box::new(Ant::new(turns)?));
+        }
+    }
+
+    let name: String = text
+        .chars()
+        .filter(|character| character.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+    match name.as_str() {
+        "wireworld" => Ok(Box::new(WireWorld)),
+        "briansbrain" => Ok(Box::new(BriansBrain)),
+        "langtonsant" => Ok(Box::new(Ant::new("RL")?)),
+        "immigration" => Ok(Box::new(Colored::immigration())),
+        "quadlife" => Ok(Box::new(Colored::quad_life())),
+        _ => match Margolus::named(&name) {
+            Some(margolus) => Ok(Box::new(margolus)),
+            None => Ok(Box::new(text.parse::<Rule>()?)),
+        },
+    }
+}
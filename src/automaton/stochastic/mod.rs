@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use super::Automaton;
+use crate::color::Color;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::grid::ALIVE;
+use crate::grid::DEAD;
+use crate::random::Random;
+use crate::rule::Rule;
+
+/// A rule whose births and deaths only happen with some probability, with
+/// cosmic rays that flip random cells between dead and alive. It is written as
+/// the rule followed by its settings, those left out being certain births and
+/// deaths and no flips: B3/S23;birth=0.95;death=0.9;flips=0.001;seed=42
+///
+/// The random numbers of a generation only depend on the seed and the
+/// generation, so a run can be replayed, even from a resumed session.
+pub struct Stochastic {
+    rule: Rule,
+    birth: f64,
+    death: f64,
+    flips: f64,
+    seed: u64,
+}
+
+impl Stochastic {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = |reason: String| {
+            Error::InvalidRule(format!("invalid stochastic rule \"{text}\": {reason}"))
+        };
+        let mut settings = text.split(';');
+        let mut stochastic = Stochastic {
+            rule: settings.next().unwrap_or_default().parse()?,
+            birth: 1.0,
+            death: 1.0,
+            flips: 0.0,
+            // Short enough to read in the ribbon
+            seed: Random::time_seed() % 1_000_000,
+        };
+
+        for setting in settings
+            .map(str::trim)
+            .filter(|setting| !setting.is_empty())
+        {
+            let Some((name, value)) = setting.split_once('=') else {
+                return Err(invalid(format!("\"{setting}\" is not name=value")));
+            };
+            if name == "seed" {
+                stochastic.seed = value
+                    .parse()
+                    .map_err(|_| invalid("the seed is a positive integer".to_string()))?;
+                continue;
+            }
+
+            let probability = match value.parse::<f64>() {
+                Ok(probability) if (0.0..=1.0).contains(&probability) => probability,
+                _ => return Err(invalid(format!("{name} is a probability from 0 to 1"))),
+            };
+            match name {
+                "birth" => stochastic.birth = probability,
+                "death" => stochastic.death = probability,
+                "flips" => stochastic.flips = probability,
+                _ => return Err(invalid(format!("unknown setting \"{name}\""))),
+            }
+        }
+        Ok(stochastic)
+    }
+}
+
+impl Automaton for Stochastic {
+    fn name(&self) -> String {
+        let mut name = self.rule.name();
+        for (setting, probability, default) in [
+            ("birth", self.birth, 1.0),
+            ("death", self.death, 1.0),
+            ("flips", self.flips, 0.0),
+        ] {
+            if probability != default {
+                name.push_str(&format!(";{setting}={probability}"));
+            }
+        }
+        format!("{name};seed={}", self.seed)
+    }
+
+    fn states(&self) -> u8 {
+        self.rule.states()
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let generation = (grid.generation as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut random = Random::new(self.seed ^ generation);
+
+        // Births and deaths of the rule each happen with their probability
+        let mut changed_cells: BTreeMap<(u16, u16), u8> = BTreeMap::new();
+        for (index, state) in self.rule.step(grid) {
+            let probability = match (grid[index], state) {
+                (ALIVE, _) => self.death,
+                (_, ALIVE) => self.birth,
+                _ => 1.0,
+            };
+            if random.chance(probability) {
+                changed_cells.insert(index, state);
+            }
+        }
+
+        if self.flips > 0.0 {
+            for height in 0..grid.height {
+                for width in 0..grid.width {
+                    let index = (width, height);
+                    if !random.chance(self.flips) {
+                        continue;
+                    }
+                    let state = changed_cells.get(&index).copied().unwrap_or(grid[index]);
+                    match state {
+                        DEAD => changed_cells.insert(index, ALIVE),
+                        ALIVE => changed_cells.insert(index, DEAD),
+                        _ => None,
+                    };
+                }
+            }
+        }
+
+        changed_cells
+            .into_iter()
+            .filter(|(index, state)| grid[*index] != *state)
+            .collect()
+    }
+
+    fn color(&self, index: (u16, u16), state: u8) -> Color {
+        self.rule.color(index, state)
+    }
+
+    fn clicked_state(&self, state: u8) -> u8 {
+        self.rule.clicked_state(state)
+    }
+
+    fn is_staggered(&self) -> bool {
+        self.rule.is_staggered()
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn soup(rule: &str) -> Grid {
+        let mut grid = Grid::new(20, 20);
+        grid.set_automaton(Box::new(Stochastic::parse(rule).unwrap()));
+        let mut random = Random::new(7);
+        for height in 0..20 {
+            for width in 0..20 {
+                if random.chance(0.4) {
+                    grid.set_cell((width, height), ALIVE);
+                }
+            }
+        }
+        for _ in 0..5 {
+            grid.next_generation();
+        }
+        grid
+    }
+
+    fn cells(grid: &Grid) -> Vec<u8> {
+        (0..grid.height)
+            .flat_map(|height| (0..grid.width).map(move |width| grid[(width, height)]))
+            .collect()
+    }
+
+    #[test]
+    fn seeds_replay_the_same_run() {
+        let rule = "B3/S23;birth=0.9;death=0.5;flips=0.01;seed=42";
+        assert_eq!(cells(&soup(rule)), cells(&soup(rule)));
+        assert_ne!(
+            cells(&soup(rule)),
+            cells(&soup("B3/S23;birth=0.9;death=0.5;flips=0.01;seed=43"))
+        );
+
+        // Without births the population can only shrink
+        let grid = soup("B3/S23;birth=0;seed=1");
+        assert_eq!(grid.births, 0);
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let rule = Stochastic::parse("B3/S23;seed=5;birth=0.95").unwrap();
+        assert_eq!(rule.name(), "B3/S23;birth=0.95;seed=5");
+        for text in [
+            "B3/S23;birth=2",
+            "B3/S23;seed=-1",
+            "B3/S23;rays=0.1",
+            "B3/S23;birth",
+        ] {
+            assert!(Stochastic::parse(text).is_err(), "{text} should be invalid");
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use super::Automaton;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::grid::Grid;
+
+/// Electrons move along wires: a head becomes a tail, a tail becomes a conductor
+/// again, and a conductor next to one or two heads becomes a head.
+/// <https://conwaylife.com/wiki/WireWorld>
+pub struct WireWorld;
+
+const EMPTY: u8 = 0;
+// Heads are the live state, so the population is the number of electrons
+const HEAD: u8 = 1;
+const TAIL: u8 = 2;
+const CONDUCTOR: u8 = 3;
+
+impl Automaton for WireWorld {
+    fn name(&self) -> String {
+        "WireWorld".to_string()
+    }
+
+    fn states(&self) -> u8 {
+        4
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let mut changed_cells = Vec::new();
+        for width in 0..grid.width {
+            for height in 0..grid.height {
+                let index = (width, height);
+                let next_state = match grid[index] {
+                    HEAD => TAIL,
+                    TAIL => CONDUCTOR,
+                    CONDUCTOR if matches!(grid.neighborhood(index).count_ones(), 1 | 2) => HEAD,
+                    _ => continue,
+                };
+                changed_cells.push((index, next_state));
+            }
+        }
+        changed_cells
+    }
+
+    fn color(&self, _index: (u16, u16), state: u8) -> Color {
+        match state {
+            HEAD => Color::Blue,
+            TAIL => Color::Red,
+            CONDUCTOR => Color::Yellow,
+            _ => BACKGROUND_COLOR,
+        }
+    }
+
+    // Wires are drawn first, then electrons are put on them
+    fn clicked_state(&self, state: u8) -> u8 {
+        match state {
+            EMPTY => CONDUCTOR,
+            CONDUCTOR => HEAD,
+            HEAD => TAIL,
+            _ => EMPTY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn electrons_move_along_wires() {
+        let mut grid = Grid::new(6, 3);
+        grid.set_automaton(Box::new(WireWorld));
+        for width in 0..6 {
+            grid.set_cell((width, 1), CONDUCTOR);
+        }
+        grid.set_cell((1, 1), TAIL);
+        grid.set_cell((2, 1), HEAD);
+
+        grid.next_generation();
+        assert_eq!(
+            [grid[(1, 1)], grid[(2, 1)], grid[(3, 1)]],
+            [CONDUCTOR, TAIL, HEAD]
+        );
+        assert_eq!(grid.population, 1);
+
+        grid.next_generation();
+        assert_eq!([grid[(3, 1)], grid[(4, 1)]], [TAIL, HEAD]);
+        assert_eq!(grid[(1, 1)], CONDUCTOR);
+    }
+}
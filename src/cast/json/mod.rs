@@ -0,0 +1,242 @@
+// Just enough JSON to read and write asciicast files
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(PartialEq, Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut chars = text.chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(value),
+        Some(character) => Err(format!("unexpected '{character}' after JSON value")),
+    }
+}
+
+// Quote and escape a string
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character if (character as u32) < 0x20 || character == '\u{7f}' => {
+                escaped.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars
+        .next_if(|character| character.is_whitespace())
+        .is_some()
+    {}
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    skip_whitespace(chars);
+    match chars.next() {
+        Some(character) if character == expected => Ok(()),
+        Some(character) => Err(format!("expected '{expected}' but found '{character}'")),
+        None => Err(format!("expected '{expected}' but the JSON ended")),
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('{') => {
+            chars.next();
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Ok(Value::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                let name = parse_string(chars)?;
+                expect(chars, ':')?;
+                members.push((name, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Value::Object(members)),
+                    _ => return Err("expected ',' or '}' in object".to_string()),
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Ok(Value::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Value::Array(values)),
+                    _ => return Err("expected ',' or ']' in array".to_string()),
+                }
+            }
+        }
+        Some('"') => Ok(Value::String(parse_string(chars)?)),
+        Some(character) if *character == '-' || character.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(character) = chars.next_if(|character| {
+                character.is_ascii_digit() || matches!(character, '-' | '+' | '.' | 'e' | 'E')
+            }) {
+                number.push(character);
+            }
+            number
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("invalid number {number}"))
+        }
+        Some(_) => {
+            let mut word = String::new();
+            while let Some(character) = chars.next_if(char::is_ascii_alphabetic) {
+                word.push(character);
+            }
+            match word.as_str() {
+                "null" => Ok(Value::Null),
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(format!("unexpected \"{word}\" in JSON")),
+            }
+        }
+        None => Err("expected a value but the JSON ended".to_string()),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err("expected a string".to_string());
+    }
+
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('"') => text.push('"'),
+                Some('\\') => text.push('\\'),
+                Some('/') => text.push('/'),
+                Some('b') => text.push('\u{8}'),
+                Some('f') => text.push('\u{c}'),
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some('t') => text.push('\t'),
+                Some('u') => {
+                    let mut code = parse_code_unit(chars)?;
+                    // Characters outside the basic plane are written as surrogate pairs
+                    if (0xd800..0xdc00).contains(&code) {
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err("unpaired surrogate in string".to_string());
+                        }
+                        let low = parse_code_unit(chars)?;
+                        code =
+                            0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                    }
+                    text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                _ => return Err("invalid escape in string".to_string()),
+            },
+            Some(character) => text.push(character),
+        }
+    }
+}
+
+fn parse_code_unit(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let hex: String = chars.take(4).collect();
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape \\u{hex} in string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_strings_round_trip() {
+        let text = "\u{1b}[2;3H\"quoted\"\\ \t\u{7f} \u{1F600}";
+
+        assert_eq!(parse(&escape(text)), Ok(Value::String(text.to_string())));
+        assert_eq!(
+            parse("\"\\ud83d\\ude00\""),
+            Ok(Value::String("\u{1F600}".to_string()))
+        );
+    }
+
+    #[test]
+    fn nested_values() {
+        let value =
+            parse(r#" {"version": 2, "env": {"TERM": "xterm"}, "tags": [true, null, -1.5e1]} "#)
+                .unwrap();
+
+        assert_eq!(value.get("version").and_then(Value::as_f64), Some(2.0));
+        assert_eq!(
+            value
+                .get("env")
+                .and_then(|env| env.get("TERM"))
+                .and_then(Value::as_str),
+            Some("xterm")
+        );
+        assert_eq!(
+            value.get("tags").and_then(Value::as_array),
+            Some(&[Value::Bool(true), Value::Null, Value::Number(-15.0)][..])
+        );
+    }
+}
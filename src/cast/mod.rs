@@ -0,0 +1,313 @@
+mod json;
+
+use crossterm::cursor;
+use crossterm::event::poll;
+use crossterm::event::read;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyModifiers;
+use crossterm::execute;
+use crossterm::queue;
+use crossterm::style::Print;
+use crossterm::terminal;
+use crossterm::terminal::Clear;
+use crossterm::terminal::ClearType;
+use crossterm::terminal::EnterAlternateScreen;
+use crossterm::terminal::SetSize;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::stdout;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use crate::exit::Error;
+use crate::printer::TerminalGuard;
+
+// Replay speeds can be changed between these bounds while replaying
+const MIN_SPEED: f64 = 1.0 / 16.0;
+const MAX_SPEED: f64 = 16.0;
+// Long pauses are waited for in steps of this, which keeps polls short
+const MAX_WAIT: Duration = Duration::from_secs(1);
+
+// Writes what is sent to the terminal as an asciicast v2 file:
+// a JSON header line, then one [time, type, data] JSON array per line.
+// https://docs.asciinema.org/manual/asciicast/v2/
+pub struct CastRecorder {
+    file: BufWriter<File>,
+    start: Instant,
+    // Output written since the last flush, recorded as one event when flushed
+    pending: Vec<u8>,
+}
+
+impl CastRecorder {
+    pub fn create(path: &Path, width: u16, height: u16) -> Result<Self, io::Error> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        writeln!(
+            file,
+            r#"{{"version": 2, "width": {width}, "height": {height}, "timestamp": {timestamp}, "env": {{"TERM": {}}}}}"#,
+            json::escape(&term)
+        )?;
+
+        Ok(CastRecorder {
+            file,
+            start: Instant::now(),
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<(), io::Error> {
+        self.write_pending()?;
+        self.write_event("r", &format!("{width}x{height}"))
+    }
+
+    pub fn finish(mut self) -> Result<(), io::Error> {
+        self.write_pending()?;
+        self.file.flush()
+    }
+
+    fn write_pending(&mut self) -> Result<(), io::Error> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let data = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        self.write_event("o", &data)
+    }
+
+    fn write_event(&mut self, kind: &str, data: &str) -> Result<(), io::Error> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(
+            self.file,
+            "[{time:.6}, {}, {}]",
+            json::escape(kind),
+            json::escape(data)
+        )
+    }
+}
+
+// Passes everything through to the terminal, and to the recording if there is one
+pub struct RecordedOutput<W: Write> {
+    output: W,
+    pub recorder: Option<CastRecorder>,
+}
+
+impl<W: Write> RecordedOutput<W> {
+    pub fn new(output: W, recorder: Option<CastRecorder>) -> Self {
+        RecordedOutput { output, recorder }
+    }
+}
+
+impl<W: Write> Write for RecordedOutput<W> {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let written = self.output.write(buffer)?;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.pending.extend_from_slice(&buffer[..written]);
+        }
+        Ok(written)
+    }
+
+    // Everything written between two flushes shows up at once, so it is one event
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.output.flush()?;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.write_pending()?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Cast {
+    pub width: u16,
+    pub height: u16,
+    pub events: Vec<CastEvent>,
+}
+
+pub struct CastEvent {
+    pub time: f64,
+    pub kind: String,
+    pub data: String,
+}
+
+impl Cast {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let invalid =
+            |line: usize, message: String| Error::Pattern(format!("line {}: {message}", line + 1));
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| invalid(0, "the cast is empty".to_string()))?;
+        let header = json::parse(header).map_err(|error| invalid(0, error))?;
+        if header.get("version").and_then(json::Value::as_f64) != Some(2.0) {
+            return Err(invalid(
+                0,
+                "only asciicast version 2 is supported".to_string(),
+            ));
+        }
+        let dimension = |name: &str| {
+            header
+                .get(name)
+                .and_then(json::Value::as_f64)
+                .filter(|dimension| (1.0..=u16::MAX as f64).contains(dimension))
+                .map(|dimension| dimension as u16)
+                .ok_or_else(|| invalid(0, format!("invalid {name} in header")))
+        };
+        let (width, height) = (dimension("width")?, dimension("height")?);
+
+        let mut events = Vec::new();
+        for (line_index, line) in lines {
+            let event = json::parse(line).map_err(|error| invalid(line_index, error))?;
+            match event.as_array() {
+                Some([time, kind, data]) => events.push(CastEvent {
+                    // Times are seconds that the slowest replay can wait for
+                    time: time
+                        .as_f64()
+                        .filter(|time| Duration::try_from_secs_f64(time / MIN_SPEED).is_ok())
+                        .ok_or_else(|| invalid(line_index, "invalid event time".to_string()))?,
+                    kind: kind.as_str().unwrap_or_default().to_string(),
+                    data: data.as_str().unwrap_or_default().to_string(),
+                }),
+                _ => return Err(invalid(line_index, "invalid event".to_string())),
+            }
+        }
+
+        Ok(Cast {
+            width,
+            height,
+            events,
+        })
+    }
+}
+
+// Replay the output of a cast in the terminal and wait for a key at the end.
+// While replaying, + and - change the speed and q stops.
+pub fn replay(path: &Path, mut speed: f64) -> Result<(), Error> {
+    let cast = Cast::parse(&fs::read_to_string(path)?)?;
+    let mut stdout = stdout();
+    let terminal_size = terminal::size().map_err(Error::Terminal)?;
+
+    // Terminals that support it are resized to the size of the recording
+    let guard = TerminalGuard::enter().map_err(Error::Terminal)?;
+    execute!(
+        stdout,
+        SetSize(cast.width, cast.height),
+        EnterAlternateScreen,
+        Clear(ClearType::All),
+        cursor::MoveTo(0, 0)
+    )
+    .map_err(Error::Terminal)?;
+
+    // Seconds of the cast that have been replayed so far
+    let mut position = 0.0;
+    let mut is_stopped = false;
+    'events: for event in &cast.events {
+        while position < event.time {
+            let started_waiting = Instant::now();
+            let remaining = Duration::try_from_secs_f64((event.time - position) / speed)
+                .map_err(|_| Error::Pattern(format!("invalid event time {}", event.time)))?;
+            let remaining = remaining.min(MAX_WAIT);
+            if poll(remaining).map_err(Error::Terminal)? {
+                if let Event::Key(key_event) = read().map_err(Error::Terminal)? {
+                    match (key_event.code, key_event.modifiers) {
+                        (KeyCode::Char('c'), KeyModifiers::CONTROL)
+                        | (KeyCode::Char('q'), KeyModifiers::NONE) => {
+                            is_stopped = true;
+                            break 'events;
+                        }
+                        (KeyCode::Char('+'), KeyModifiers::NONE) => {
+                            speed = (speed * 2.0).min(MAX_SPEED)
+                        }
+                        (KeyCode::Char('-'), KeyModifiers::NONE) => {
+                            speed = (speed / 2.0).max(MIN_SPEED)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            position += started_waiting.elapsed().as_secs_f64() * speed;
+        }
+
+        match event.kind.as_str() {
+            "o" => queue!(stdout, Print(&event.data)).map_err(Error::Terminal)?,
+            "r" => {
+                if let Some((width, height)) = event.data.split_once('x') {
+                    if let (Ok(width), Ok(height)) = (width.parse(), height.parse()) {
+                        queue!(stdout, SetSize(width, height)).map_err(Error::Terminal)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        stdout.flush().map_err(Error::Terminal)?;
+    }
+
+    while !is_stopped {
+        is_stopped = matches!(read().map_err(Error::Terminal)?, Event::Key(_));
+    }
+
+    guard.restore().map_err(Error::Terminal)?;
+    execute!(stdout, SetSize(terminal_size.0, terminal_size.1)).map_err(Error::Terminal)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_output_is_replayable() {
+        let path = env::temp_dir().join(format!("game-of-life-test-{}.cast", std::process::id()));
+        let recorder = CastRecorder::create(&path, 80, 24).unwrap();
+        let mut output = RecordedOutput::new(Vec::new(), Some(recorder));
+
+        write!(output, "\u{1b}[1;1HGeneration: 1").unwrap();
+        output.flush().unwrap();
+        output.recorder.as_mut().unwrap().resize(100, 30).unwrap();
+        write!(output, "\"quoted\"\r\n").unwrap();
+        output.flush().unwrap();
+        output.recorder.take().unwrap().finish().unwrap();
+
+        let cast = Cast::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((cast.width, cast.height), (80, 24));
+        let events: Vec<(&str, &str)> = cast
+            .events
+            .iter()
+            .map(|event| (event.kind.as_str(), event.data.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("o", "\u{1b}[1;1HGeneration: 1"),
+                ("r", "100x30"),
+                ("o", "\"quoted\"\r\n")
+            ]
+        );
+        assert_eq!(output.output, b"\x1b[1;1HGeneration: 1\"quoted\"\r\n");
+    }
+
+    #[test]
+    fn invalid_times_are_rejected() {
+        let header = "{\"version\": 2, \"width\": 80, \"height\": 24}\n";
+        for time in ["-1", "1e300", "1e400", "\"1\""] {
+            let text = format!("{header}[{time}, \"o\", \"text\"]\n");
+            assert!(Cast::parse(&text).is_err(), "{time} should be invalid");
+        }
+        assert!(Cast::parse(&format!("{header}[0.5, \"o\", \"text\"]\n")).is_ok());
+    }
+}
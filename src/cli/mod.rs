@@ -0,0 +1,559 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use game_of_life::apgcode;
+use game_of_life::automaton;
+use game_of_life::automaton::Automaton;
+use game_of_life::automaton::InitialRow;
+use game_of_life::export::parse_hex_color;
+use game_of_life::export::save_png;
+use game_of_life::export::save_svg;
+use game_of_life::export::GifRecorder;
+use game_of_life::export::ImageStyle;
+use game_of_life::export::Region;
+use game_of_life::grid::Anchor;
+use game_of_life::grid::Grid;
+use game_of_life::macrocell;
+use game_of_life::macrocell::Macrocell;
+use game_of_life::session;
+
+use crate::app::keymap::KeyMap;
+use crate::app::script;
+use crate::app::script::Script;
+use crate::exit::Error;
+
+pub const USAGE: &str = "\
+Usage:
+    game-of-life [PLAY OPTIONS]       Play in the terminal
+    game-of-life record OUTPUT [PLAY OPTIONS]
+                                      Play in the terminal, recording an asciicast
+    game-of-life replay INPUT [--speed FACTOR]
+                                      Replay an asciicast (+/- change the speed)
+    game-of-life gif OUTPUT [OPTIONS] Export generations as an animated GIF
+    game-of-life png OUTPUT [OPTIONS] Export a generation as a PNG image
+    game-of-life svg OUTPUT [OPTIONS] Export a generation as an SVG image
+    game-of-life mc OUTPUT [OPTIONS]  Export a generation as a Macrocell pattern
+    game-of-life apgcode [--session PATH]
+                                      Print the apgcode of a saved pattern
+    game-of-life help                 Show this message
+
+Rules are written in B/S notation (B3/S23), S/B/C notation for Generations
+rules (345/2/4), or by name (Life, HighLife, Seeds, StarWars, tlife).
+Neighbor counts can be followed by Hensel letters (B2-a/S12).
+A suffix selects another neighborhood: V for von Neumann (B13/S012V), H for
+hexagonal (B2/S34H), or N@ and a hexadecimal mask of the cells within a radius
+of 1 to 3, row by row and skipping the center (B3/S23N@201804). Neighborhoods
+of more than 9 cells separate their counts with commas (B3,12/S2,3N@ffffff).
+Larger than Life rules count the cells within a radius of up to 10, in Golly's
+notation (R5,C0,M1,S34..58,B34..45,NM) or HROT's (R2,C0,M0,S2-3,5,B3,NM), and
+Bosco and Majority name two of them.
+Automata that are not Life-like are given by name: WireWorld, BriansBrain,
+LangtonsAnt, or Ant: and the turns of a turmite for each color (Ant:LLRR).
+Clicking a cell moves it to the next state.
+Stochastic rules follow a rule with the probabilities of births and deaths and
+the rate of random flips, and the seed that makes runs repeatable, separated by
+semicolons: 'B3/S23;birth=0.95;death=0.9;flips=0.001;seed=42'. Left out, births
+and deaths are certain, there are no flips, and the seed is random.
+Immigration and QuadLife are Life with cells of 2 or 4 colors, where a cell
+that is born takes the color most of its parents have. The number keys pick
+the color clicks paint.
+Elementary automata run on the top row and scroll their history down: W30, or
+W110:T to wrap around the edges. --initial sets the top row to a single live
+cell in the middle (single), random cells (random), or cells like ..O.OO.
+Block rules replace 2x2 blocks, at offsets that alternate every generation,
+with the blocks listed for each of the 16 blocks
+(M/0,8,4,3,2,5,9,7,1,6,10,11,12,13,14,15), and Critters, Tron and
+BilliardBallModel name three of them.
+
+--pattern plays a pattern of Golly's Macrocell format (.mc) in the middle of
+the grid, in the rule of the file unless --rule is given. Whatever doesn't fit
+in the terminal is left out.
+
+Pasting an apgcode (xq4_153) while playing adds its object in the middle of
+the grid, and a shows the apgcode of the pattern.
+
+--save-script writes what is typed, clicked and pasted to a script, with the
+size of the terminal, the rule, the key map and the starting cells, and
+--script plays it again as it was played, until it ends or q is pressed. Scripts are text files with one input
+per line after the milliseconds since the start: key p, click 10 5,
+paste xq4_153, resize 100 30. Scripted games don't resume the saved session.
+
+Making the terminal smaller hides the cells that don't fit, and making it
+larger again brings them back. The grid grows and shrinks around its center,
+or keeps its top left corner in place, or centers the bounding box or the
+center of mass of the live cells: o picks the next anchor while playing.
+
+? shows every action with its keys. A key map rebinds them, one line per
+action with its keys separated by commas, and the keys of a sequence separated
+by spaces: 'pause = p, space' or 'snapshot = ctrl+x s'. It is read from
+--keys, or from ~/.config/game-of-life/keys. Ctrl+C always quits.
+
+: opens a command line at the bottom, where tab completes commands and file
+names and up and down go through the commands typed before:
+    :rule B36/S23            :step 100         :random 0.3 seed=42
+    :load glider.rle         :goto 5000        :topology torus
+    :save glider.rle         :speed 20         :clear
+Patterns are loaded and saved as RLE (.rle) or Macrocell (.mc) files. The
+actions of the key map are commands too: :pause, :snapshot. Commands that fail
+say why in place of the bottom ribbon. Long steps and gotos can be stopped with
+any key.
+
+Exported patterns are read from a saved session (the one saved on quit by
+default). Macrocell exports are limited to two-state rules.
+
+Errors exit with 64 for invalid arguments or key maps, 65 for a rule, pattern,
+session, script or cast that can't be read, 71 when the terminal fails, and 74
+when a file can't be read or written. The terminal is restored however the game
+ends.
+
+Play options:
+    --rule RULE               Rule of the game
+    --initial ROW             Top row of an elementary automaton
+    --pattern FILE            Macrocell pattern to play
+    --script FILE             Play a script in place of the keyboard and the mouse
+    --save-script FILE        Write the inputs of the game to a script
+    --keys FILE               Key map to play with
+    --anchor ANCHOR           What stays in place when the terminal is resized:
+                              center, top-left, bounding-box or center-of-mass
+    --max-world COLUMNS,ROWS  Keep the cells that don't fit in a smaller terminal
+                              within a world this large (default: 4096,4096)
+
+Options:
+    --session PATH            Session file to read the pattern from
+    --from GENERATION         First generation of a GIF (default: the session's)
+    --to GENERATION           Last generation of a GIF (default: 100 after --from)
+    --delay MILLISECONDS      Delay between the frames of a GIF (default: 100)
+    --generation GENERATION   Generation of a PNG, SVG or Macrocell (default: the session's)
+    --region LEFT,TOP,COLUMNS,ROWS
+                              Export only these cells of a PNG, SVG or Macrocell
+    --cell-size PIXELS        Size of one cell (default: 8)
+    --padding PIXELS          Empty space around the cells (default: 0)
+    --cell-color RRGGBB       Color of live cells
+    --background-color RRGGBB Color of dead cells
+    --grid-lines              Draw lines between cells
+    --grid-line-color RRGGBB  Color of the lines between cells (implies --grid-lines)
+    --age-colors              Fade cells to another color as they get older
+    --old-cell-color RRGGBB   Color of the oldest cells (implies --age-colors)";
+
+pub enum Command {
+    Play(PlayOptions),
+    Help,
+    Replay { input: PathBuf, speed: f64 },
+    Gif(GifExport),
+    Snapshot(SnapshotExport),
+    Apgcode { session: Option<PathBuf> },
+}
+
+#[derive(Default)]
+pub struct PlayOptions {
+    // Record the terminal to this asciicast
+    pub cast: Option<PathBuf>,
+    pub rule: Option<Box<dyn Automaton>>,
+    pub initial_row: Option<InitialRow>,
+    pub pattern: Option<Macrocell>,
+    // Play this script in place of the keyboard and the mouse
+    pub script: Option<Script>,
+    // Write the inputs of the game to this script
+    pub save_script: Option<PathBuf>,
+    pub max_world_size: Option<(u16, u16)>,
+    pub anchor: Option<Anchor>,
+    // Bind the keys of this key map in place of the configured one
+    pub keymap: Option<KeyMap>,
+}
+
+pub struct GifExport {
+    output: PathBuf,
+    session: Option<PathBuf>,
+    from: Option<usize>,
+    to: Option<usize>,
+    frame_delay: Duration,
+    style: ImageStyle,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum SnapshotFormat {
+    Png,
+    Svg,
+    Macrocell,
+}
+
+pub struct SnapshotExport {
+    format: SnapshotFormat,
+    output: PathBuf,
+    session: Option<PathBuf>,
+    generation: Option<usize>,
+    region: Option<Region>,
+    style: ImageStyle,
+}
+
+pub fn parse(arguments: impl IntoIterator<Item = String>) -> Result<Command, Error> {
+    let mut arguments = Arguments(arguments.into_iter().collect());
+    let command = match arguments.0.front() {
+        None => return Ok(Command::Play(PlayOptions::default())),
+        Some(argument) if argument.starts_with("--") && argument != "--help" => {
+            return Ok(Command::Play(arguments.play_options(None)?))
+        }
+        Some(_) => arguments.0.pop_front().unwrap_or_default(),
+    };
+
+    match command.as_str() {
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        "record" => {
+            let output = PathBuf::from(arguments.value("OUTPUT")?);
+            Ok(Command::Play(arguments.play_options(Some(output))?))
+        }
+        "replay" => {
+            let input = PathBuf::from(arguments.value("INPUT")?);
+            let mut speed: f64 = 1.0;
+            while let Some(argument) = arguments.0.pop_front() {
+                match argument.as_str() {
+                    "--speed" => speed = arguments.parse(&argument)?,
+                    _ => return Err(invalid_input(format!("unexpected argument \"{argument}\""))),
+                }
+            }
+            if !(speed > 0.0 && speed.is_finite()) {
+                return Err(invalid_input("--speed must be positive".to_string()));
+            }
+            Ok(Command::Replay { input, speed })
+        }
+        "gif" => {
+            let mut output = None;
+            let mut session = None;
+            let mut from = None;
+            let mut to = None;
+            let mut frame_delay = Duration::from_millis(100);
+            let mut style = ImageStyle::default();
+
+            while let Some(argument) = arguments.0.pop_front() {
+                match argument.as_str() {
+                    "--session" => session = Some(PathBuf::from(arguments.value(&argument)?)),
+                    "--from" => from = Some(arguments.parse(&argument)?),
+                    "--to" => to = Some(arguments.parse(&argument)?),
+                    "--delay" => frame_delay = Duration::from_millis(arguments.parse(&argument)?),
+                    _ if arguments.style_flag(&argument, &mut style)? => {}
+                    _ if output.is_none() && !argument.starts_with("--") => {
+                        output = Some(PathBuf::from(argument))
+                    }
+                    _ => return Err(invalid_input(format!("unexpected argument \"{argument}\""))),
+                }
+            }
+
+            Ok(Command::Gif(GifExport {
+                output: output.ok_or_else(|| invalid_input("missing OUTPUT".to_string()))?,
+                session,
+                from,
+                to,
+                frame_delay,
+                style,
+            }))
+        }
+        "png" | "svg" | "mc" => {
+            let format = match command.as_str() {
+                "png" => SnapshotFormat::Png,
+                "svg" => SnapshotFormat::Svg,
+                _ => SnapshotFormat::Macrocell,
+            };
+            let mut output = None;
+            let mut session = None;
+            let mut generation = None;
+            let mut region = None;
+            let mut style = ImageStyle::default();
+
+            while let Some(argument) = arguments.0.pop_front() {
+                match argument.as_str() {
+                    "--session" => session = Some(PathBuf::from(arguments.value(&argument)?)),
+                    "--generation" => generation = Some(arguments.parse(&argument)?),
+                    "--region" => {
+                        let value = arguments.value(&argument)?;
+                        region = Some(Region::parse(&value).ok_or_else(|| {
+                            invalid_input(format!("invalid region \"{value}\" for --region"))
+                        })?);
+                    }
+                    _ if arguments.style_flag(&argument, &mut style)? => {}
+                    _ if output.is_none() && !argument.starts_with("--") => {
+                        output = Some(PathBuf::from(argument))
+                    }
+                    _ => return Err(invalid_input(format!("unexpected argument \"{argument}\""))),
+                }
+            }
+
+            Ok(Command::Snapshot(SnapshotExport {
+                format,
+                output: output.ok_or_else(|| invalid_input("missing OUTPUT".to_string()))?,
+                session,
+                generation,
+                region,
+                style,
+            }))
+        }
+        "apgcode" => {
+            let mut session = None;
+            while let Some(argument) = arguments.0.pop_front() {
+                match argument.as_str() {
+                    "--session" => session = Some(PathBuf::from(arguments.value(&argument)?)),
+                    _ => return Err(invalid_input(format!("unexpected argument \"{argument}\""))),
+                }
+            }
+            Ok(Command::Apgcode { session })
+        }
+        _ => Err(invalid_input(format!("unknown command \"{command}\""))),
+    }
+}
+
+impl GifExport {
+    pub fn run(self) -> Result<(), Error> {
+        let mut grid = load_grid(self.session)?;
+        let from = self.from.unwrap_or(grid.generation);
+        let to = self.to.unwrap_or(from + 100);
+        if to < from {
+            return Err(invalid_input(format!(
+                "cannot export generations {from} to {to}"
+            )));
+        }
+
+        advance_to(&mut grid, from)?;
+        let mut recorder = GifRecorder::create(&self.output, grid.width, grid.height, self.style)?;
+        loop {
+            recorder.add_frame(&grid, self.frame_delay)?;
+            if grid.generation == to {
+                break;
+            }
+            grid.next_generation();
+        }
+        Ok(recorder.finish()?)
+    }
+}
+
+impl SnapshotExport {
+    pub fn run(self) -> Result<(), Error> {
+        let mut grid = load_grid(self.session)?;
+        if let Some(generation) = self.generation {
+            advance_to(&mut grid, generation)?;
+        }
+
+        let region = self.region.unwrap_or(Region::whole(&grid));
+        match self.format {
+            SnapshotFormat::Png => save_png(&self.output, &grid, &region, &self.style)?,
+            SnapshotFormat::Svg => save_svg(&self.output, &grid, &region, &self.style)?,
+            SnapshotFormat::Macrocell => macrocell::save(&self.output, &grid, &region)?,
+        }
+        Ok(())
+    }
+}
+
+pub fn print_apgcode(session: Option<PathBuf>) -> Result<(), Error> {
+    let grid = load_grid(session)?;
+    let apgcode = apgcode::apgcode(&grid).ok_or_else(|| {
+        Error::Pattern(
+            "the pattern is not a still life, oscillator or spaceship that fits in 40 by 40 cells"
+                .to_string(),
+        )
+    })?;
+    println!("{apgcode}");
+    Ok(())
+}
+
+// Run the grid until it reaches the generation
+fn advance_to(grid: &mut Grid, generation: usize) -> Result<(), Error> {
+    if generation < grid.generation {
+        return Err(invalid_input(format!(
+            "cannot go back to generation {generation} from generation {}",
+            grid.generation
+        )));
+    }
+    while grid.generation < generation {
+        grid.next_generation();
+    }
+    Ok(())
+}
+
+fn load_grid(session: Option<PathBuf>) -> Result<Grid, Error> {
+    let path = session.or_else(session::default_path).ok_or_else(|| {
+        invalid_input("no session to read the pattern from, pass --session".to_string())
+    })?;
+    Ok(session::load(&path)?.grid)
+}
+
+fn invalid_input(message: String) -> Error {
+    Error::Config(message)
+}
+
+struct Arguments(VecDeque<String>);
+
+impl Arguments {
+    fn value(&mut self, flag: &str) -> Result<String, Error> {
+        self.0
+            .pop_front()
+            .ok_or_else(|| invalid_input(format!("missing value for {flag}")))
+    }
+
+    fn play_options(&mut self, cast: Option<PathBuf>) -> Result<PlayOptions, Error> {
+        let mut options = PlayOptions {
+            cast,
+            ..PlayOptions::default()
+        };
+        while let Some(argument) = self.0.pop_front() {
+            match argument.as_str() {
+                "--rule" => options.rule = Some(automaton::parse(&self.value(&argument)?)?),
+                "--initial" => options.initial_row = Some(self.value(&argument)?.parse()?),
+                "--pattern" => {
+                    options.pattern = Some(macrocell::load(&PathBuf::from(self.value(&argument)?))?)
+                }
+                "--script" => {
+                    options.script = Some(script::load(&PathBuf::from(self.value(&argument)?))?)
+                }
+                "--save-script" => options.save_script = Some(self.value(&argument)?.into()),
+                "--keys" => {
+                    options.keymap = Some(KeyMap::load(&PathBuf::from(self.value(&argument)?))?)
+                }
+                "--anchor" => {
+                    let value = self.value(&argument)?;
+                    options.anchor = Some(Anchor::from_name(&value).ok_or_else(|| {
+                        invalid_input(format!("invalid anchor \"{value}\" for --anchor"))
+                    })?);
+                }
+                "--max-world" => {
+                    let value = self.value(&argument)?;
+                    let size = value.split_once(',').and_then(|(columns, rows)| {
+                        Some((columns.parse().ok()?, rows.parse().ok()?))
+                    });
+                    options.max_world_size = Some(size.ok_or_else(|| {
+                        invalid_input(format!("invalid size \"{value}\" for --max-world"))
+                    })?);
+                }
+                _ => return Err(invalid_input(format!("unexpected argument \"{argument}\""))),
+            }
+        }
+        Ok(options)
+    }
+
+    fn parse<T: FromStr>(&mut self, flag: &str) -> Result<T, Error> {
+        let value = self.value(flag)?;
+        value
+            .parse()
+            .map_err(|_| invalid_input(format!("invalid value \"{value}\" for {flag}")))
+    }
+
+    fn color(&mut self, flag: &str) -> Result<[u8; 3], Error> {
+        let value = self.value(flag)?;
+        parse_hex_color(&value)
+            .ok_or_else(|| invalid_input(format!("invalid color \"{value}\" for {flag}")))
+    }
+
+    // Apply an image style flag, returning whether the flag was one
+    fn style_flag(&mut self, flag: &str, style: &mut ImageStyle) -> Result<bool, Error> {
+        match flag {
+            "--cell-size" => {
+                style.cell_size = self.parse(flag)?;
+                if style.cell_size == 0 {
+                    return Err(invalid_input("--cell-size must be positive".to_string()));
+                }
+            }
+            "--padding" => style.padding = self.parse(flag)?,
+            "--cell-color" => style.cell_color = self.color(flag)?,
+            "--background-color" => style.background_color = self.color(flag)?,
+            "--grid-lines" => {
+                style.grid_line_color = style.grid_line_color.or(Some([64, 64, 64]));
+            }
+            "--grid-line-color" => style.grid_line_color = Some(self.color(flag)?),
+            "--age-colors" => {
+                style.old_cell_color = style.old_cell_color.or(Some([255, 64, 0]));
+            }
+            "--old-cell-color" => style.old_cell_color = Some(self.color(flag)?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn gif_options() {
+        let command = parse(arguments(
+            "gif out.gif --to 20 --cell-size 4 --grid-lines --cell-color #00ff00",
+        ))
+        .unwrap();
+
+        let Command::Gif(export) = command else {
+            panic!("The command is not gif!");
+        };
+        assert_eq!(export.output, PathBuf::from("out.gif"));
+        assert_eq!(export.to, Some(20));
+        assert_eq!(export.style.cell_size, 4);
+        assert_eq!(export.style.cell_color, [0, 255, 0]);
+        assert!(export.style.grid_line_color.is_some());
+    }
+
+    #[test]
+    fn snapshot_options() {
+        let command = parse(arguments(
+            "svg out.svg --region 1,2,30,40 --padding 3 --age-colors --generation 7",
+        ))
+        .unwrap();
+
+        let Command::Snapshot(export) = command else {
+            panic!("The command is not a snapshot!");
+        };
+        assert_eq!(export.format, SnapshotFormat::Svg);
+        assert_eq!(
+            export.region,
+            Some(Region {
+                left: 1,
+                top: 2,
+                columns: 30,
+                rows: 40
+            })
+        );
+        assert_eq!(export.generation, Some(7));
+        assert_eq!(export.style.padding, 3);
+        assert!(export.style.old_cell_color.is_some());
+    }
+
+    #[test]
+    fn play_options() {
+        let Command::Play(options) = parse(arguments("--rule 345/2/4")).unwrap() else {
+            panic!("The command is not play!");
+        };
+        assert_eq!(
+            options.rule.map(|rule| rule.name()),
+            Some("345/2/4".to_string())
+        );
+        assert_eq!(options.cast, None);
+
+        let Command::Play(options) = parse(arguments("--max-world 300,200")).unwrap() else {
+            panic!("The command is not play!");
+        };
+        assert_eq!(options.max_world_size, Some((300, 200)));
+
+        let Command::Play(options) = parse(arguments("--anchor top-left")).unwrap() else {
+            panic!("The command is not play!");
+        };
+        assert_eq!(options.anchor, Some(Anchor::TopLeft));
+
+        let Command::Play(options) = parse(arguments("record out.cast")).unwrap() else {
+            panic!("The command is not play!");
+        };
+        assert_eq!(options.cast, Some(PathBuf::from("out.cast")));
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected() {
+        assert!(parse(arguments("gif")).is_err());
+        assert!(parse(arguments("gif out.gif --speed 3")).is_err());
+        assert!(parse(arguments("png out.png --region 1,2,3")).is_err());
+        assert!(parse(arguments("record")).is_err());
+        assert!(parse(arguments("--rule B9")).is_err());
+        assert!(parse(arguments("replay a.cast --speed 0")).is_err());
+        assert!(parse(arguments("draw")).is_err());
+        assert!(parse(arguments("--max-world 300")).is_err());
+    }
+}
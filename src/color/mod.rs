@@ -0,0 +1,77 @@
+/// The colors automata give their cells, as the 16 colors of terminals or as
+/// RGB colors. The terminal interface draws the named colors in the palette of
+/// the terminal, images use the RGB values of the standard palette.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Color {
+    /// #000000 in images
+    Black,
+    /// #808080 in images
+    DarkGrey,
+    /// #ff0000 in images
+    Red,
+    /// #800000 in images
+    DarkRed,
+    /// #00ff00 in images
+    Green,
+    /// #008000 in images
+    DarkGreen,
+    /// #ffff00 in images
+    Yellow,
+    /// #808000 in images
+    DarkYellow,
+    /// #0000ff in images
+    Blue,
+    /// #000080 in images
+    DarkBlue,
+    /// #ff00ff in images
+    Magenta,
+    /// #800080 in images
+    DarkMagenta,
+    /// #00ffff in images
+    Cyan,
+    /// #008080 in images
+    DarkCyan,
+    /// #ffffff in images
+    White,
+    /// #c0c0c0 in images
+    Grey,
+    /// A color of red, green and blue values, drawn as is
+    Rgb {
+        /// The red value
+        r: u8,
+        /// The green value
+        g: u8,
+        /// The blue value
+        b: u8,
+    },
+}
+
+/// The color of dead cells and of the screen around them
+pub const BACKGROUND_COLOR: Color = Color::Black;
+/// The color of live cells, unless their automaton has its own
+pub const CELL_COLOR: Color = Color::Yellow;
+
+impl Color {
+    /// Approximate the RGB value terminals commonly use for a color
+    pub fn rgb(self) -> [u8; 3] {
+        match self {
+            Color::Rgb { r, g, b } => [r, g, b],
+            Color::Black => [0, 0, 0],
+            Color::DarkGrey => [128, 128, 128],
+            Color::Red => [255, 0, 0],
+            Color::DarkRed => [128, 0, 0],
+            Color::Green => [0, 255, 0],
+            Color::DarkGreen => [0, 128, 0],
+            Color::Yellow => [255, 255, 0],
+            Color::DarkYellow => [128, 128, 0],
+            Color::Blue => [0, 0, 255],
+            Color::DarkBlue => [0, 0, 128],
+            Color::Magenta => [255, 0, 255],
+            Color::DarkMagenta => [128, 0, 128],
+            Color::Cyan => [0, 255, 255],
+            Color::DarkCyan => [0, 128, 128],
+            Color::White => [255, 255, 255],
+            Color::Grey => [192, 192, 192],
+        }
+    }
+}
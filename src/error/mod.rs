@@ -0,0 +1,58 @@
+use std::error;
+use std::fmt;
+use std::fmt::Display;
+use std::io;
+
+/// What can go wrong in the library. The messages of the invalid inputs say
+/// what was read and why it is invalid.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// A rule string that names no automaton
+    InvalidRule(String),
+    /// A pattern that can't be read: an apgcode, a Macrocell file, an initial row
+    InvalidPattern(String),
+    /// A saved session that can't be read
+    InvalidSession(String),
+    /// A grid that can't be exported in a format
+    Export(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => error.fmt(formatter),
+            Error::InvalidRule(message)
+            | Error::InvalidPattern(message)
+            | Error::InvalidSession(message)
+            | Error::Export(message) => formatter.write_str(message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+// Lets code that works with io::Error use the library with `?`
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            Error::InvalidSession(_) => io::Error::new(io::ErrorKind::InvalidData, error),
+            _ => io::Error::new(io::ErrorKind::InvalidInput, error),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::error;
+use std::fmt;
+use std::fmt::Display;
+use std::io;
+use std::process::ExitCode;
+
+// What ends the game with an error. Each kind of error exits with its own code,
+// from the ones of sysexits.h, so scripts can tell them apart.
+#[derive(Debug)]
+pub enum Error {
+    // The command line or the key map asks for something that can't be done
+    Config(String),
+    // A rule, pattern, session, script or cast that can't be read, or a grid
+    // that can't be exported
+    Pattern(String),
+    // Reading or writing a file failed
+    Io(io::Error),
+    // The terminal can't be set up, read or drawn to
+    Terminal(io::Error),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Error::Config(_) => 64,
+            Error::Pattern(_) => 65,
+            Error::Terminal(_) => 71,
+            Error::Io(_) => 74,
+        })
+    }
+
+    // Errors of the library that come from drawing come from the terminal
+    pub fn terminal(error: game_of_life::Error) -> Self {
+        match error {
+            game_of_life::Error::Io(error) => Error::Terminal(error),
+            error => error.into(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(message) | Error::Pattern(message) => formatter.write_str(message),
+            Error::Io(error) => error.fmt(formatter),
+            Error::Terminal(error) => write!(formatter, "terminal error: {error}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) | Error::Terminal(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<game_of_life::Error> for Error {
+    fn from(error: game_of_life::Error) -> Self {
+        match error {
+            game_of_life::Error::Io(error) => Error::Io(error),
+            error => Error::Pattern(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_have_their_own_exit_codes() {
+        let errors = [
+            Error::Config("unknown command \"draw\"".to_string()),
+            game_of_life::Error::InvalidRule("B9".to_string()).into(),
+            Error::terminal(io::Error::other("not a terminal").into()),
+            io::Error::from(io::ErrorKind::NotFound).into(),
+        ];
+        let codes = errors.iter().map(Error::exit_code).collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [64, 65, 71, 74].map(ExitCode::from),
+            "{errors:?} exit with {codes:?}"
+        );
+        assert_eq!(errors[1].to_string(), "B9");
+    }
+}
@@ -0,0 +1,85 @@
+use gif::DisposalMethod;
+use gif::Encoder;
+use gif::EncodingError;
+use gif::Frame;
+use gif::Repeat;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+
+use super::render_indexed;
+use super::too_large;
+use super::ImageStyle;
+use super::Region;
+use crate::error::Error;
+use crate::grid::Grid;
+
+/// Writes generations of a grid as the frames of an animated GIF.
+/// Every frame has the size the grid had when the recording started.
+pub struct GifRecorder {
+    encoder: Encoder<BufWriter<File>>,
+    region: Region,
+    style: ImageStyle,
+}
+
+impl GifRecorder {
+    /// Start a GIF of the cells of a grid of this size, drawn in a style
+    pub fn create(path: &Path, columns: u16, rows: u16, style: ImageStyle) -> Result<Self, Error> {
+        let region = Region {
+            left: 0,
+            top: 0,
+            columns,
+            rows,
+        };
+        let (pixel_width, pixel_height) = region.pixel_size(&style);
+        let pixel_width = u16::try_from(pixel_width).map_err(|_| too_large(&region))?;
+        let pixel_height = u16::try_from(pixel_height).map_err(|_| too_large(&region))?;
+
+        let file = BufWriter::new(File::create(path)?);
+        // Every frame has a palette of its own colors
+        let mut encoder =
+            Encoder::new(file, pixel_width, pixel_height, &[]).map_err(encoding_error)?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(encoding_error)?;
+
+        Ok(GifRecorder {
+            encoder,
+            region,
+            style,
+        })
+    }
+
+    /// Add the current generation of the grid, shown for `delay`
+    pub fn add_frame(&mut self, grid: &Grid, delay: Duration) -> Result<(), Error> {
+        let (pixels, palette) = render_indexed(grid, &self.region, &self.style);
+        let (width, height) = self.region.pixel_size(&self.style);
+        let frame = Frame {
+            // GIF delays are in hundredths of a second
+            delay: (delay.as_millis() / 10).min(u16::MAX as u128) as u16,
+            dispose: DisposalMethod::Keep,
+            width: width as u16,
+            height: height as u16,
+            buffer: Cow::Owned(pixels),
+            palette: Some(palette.bytes()),
+            ..Frame::default()
+        };
+        self.encoder.write_frame(&frame).map_err(encoding_error)
+    }
+
+    /// End the GIF after its last frame
+    pub fn finish(self) -> Result<(), Error> {
+        let mut file = self.encoder.into_inner().map_err(encoding_error)?;
+        Ok(io::Write::flush(&mut file)?)
+    }
+}
+
+fn encoding_error(error: EncodingError) -> Error {
+    match error {
+        EncodingError::Io(error) => Error::Io(error),
+        error => Error::Export(error.to_string()),
+    }
+}
@@ -0,0 +1,351 @@
+mod animation;
+pub use animation::GifRecorder;
+
+mod snapshot;
+pub use snapshot::save_png;
+pub use snapshot::save_svg;
+
+use crate::color::BACKGROUND_COLOR;
+use crate::color::CELL_COLOR;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::grid::DEAD;
+
+// Palette indices of rendered images, cells take the indices after these
+const BACKGROUND_INDEX: u8 = 0;
+const GRID_LINE_INDEX: u8 = 1;
+
+// With age-based coloring, cells fade from the cell color to the old cell color
+// in this many shades, reaching the last one after AGE_SPAN generations.
+const AGE_SHADES: u32 = 8;
+const AGE_SPAN: u32 = 64;
+
+/// How the cells of a grid are turned into pixels
+#[derive(Clone, Copy)]
+pub struct ImageStyle {
+    /// The width and height of a cell, in pixels
+    pub cell_size: u16,
+    /// The pixels of background around the cells
+    pub padding: u16,
+    /// The color of live cells that their automaton draws in the cell color
+    pub cell_color: [u8; 3],
+    /// The color of dead cells and of the padding
+    pub background_color: [u8; 3],
+    /// The color of the lines between cells, if they are drawn
+    pub grid_line_color: Option<[u8; 3]>,
+    /// The color live cells fade to as they age, if they do
+    pub old_cell_color: Option<[u8; 3]>,
+}
+
+impl Default for ImageStyle {
+    // Match the colors of the terminal interface
+    fn default() -> Self {
+        ImageStyle {
+            cell_size: 8,
+            padding: 0,
+            cell_color: CELL_COLOR.rgb(),
+            background_color: BACKGROUND_COLOR.rgb(),
+            grid_line_color: None,
+            old_cell_color: None,
+        }
+    }
+}
+
+impl ImageStyle {
+    // The color of a cell, None if it is dead. Live cells in the cell color of
+    // their automaton take the cell color of the style, in the shade of their
+    // age, and the other states the color their automaton gives them: wires,
+    // decaying cells, species.
+    fn cell_color(&self, grid: &Grid, index: (u16, u16)) -> Option<[u8; 3]> {
+        let state = grid[index];
+        if state == DEAD {
+            return None;
+        }
+        match grid.automaton().color(index, state) {
+            CELL_COLOR if grid.is_alive(state) => {
+                Some(self.shade_color(self.shade(grid.age(index))))
+            }
+            color => Some(color.rgb()),
+        }
+    }
+
+    fn shade_count(&self) -> u32 {
+        if self.old_cell_color.is_some() {
+            AGE_SHADES
+        } else {
+            1
+        }
+    }
+
+    fn shade(&self, age: u32) -> u32 {
+        let last_shade = self.shade_count() - 1;
+        (age.min(AGE_SPAN) * last_shade).div_ceil(AGE_SPAN)
+    }
+
+    fn shade_color(&self, shade: u32) -> [u8; 3] {
+        let Some(old_cell_color) = self.old_cell_color else {
+            return self.cell_color;
+        };
+        let last_shade = self.shade_count() - 1;
+        let mut color = [0; 3];
+        for (channel, (young, old)) in color
+            .iter_mut()
+            .zip(self.cell_color.into_iter().zip(old_cell_color))
+        {
+            let blend = young as u32 * (last_shade - shade) + old as u32 * shade;
+            *channel = (blend / last_shade) as u8;
+        }
+        color
+    }
+
+    // Grid lines are drawn on every cell border, including the right and bottom ones
+    fn grid_line_width(&self) -> u32 {
+        self.grid_line_color.is_some() as u32
+    }
+}
+
+/// A rectangle of cells, in cell coordinates of the grid
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Region {
+    /// The column of the grid the region starts at
+    pub left: u16,
+    /// The row of the grid the region starts at
+    pub top: u16,
+    /// The number of columns of the region
+    pub columns: u16,
+    /// The number of rows of the region
+    pub rows: u16,
+}
+
+impl Region {
+    /// The region of every cell of the grid
+    pub fn whole(grid: &Grid) -> Self {
+        Region {
+            left: 0,
+            top: 0,
+            columns: grid.width,
+            rows: grid.height,
+        }
+    }
+
+    /// Parse a region written as LEFT,TOP,COLUMNS,ROWS
+    pub fn parse(text: &str) -> Option<Self> {
+        let numbers: Vec<u16> = text
+            .split(',')
+            .map(|number| number.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        match numbers[..] {
+            [left, top, columns, rows] if columns > 0 && rows > 0 => Some(Region {
+                left,
+                top,
+                columns,
+                rows,
+            }),
+            _ => None,
+        }
+    }
+
+    // The color of a cell of the region, None if it is dead or outside the grid
+    fn cell_color(
+        &self,
+        grid: &Grid,
+        style: &ImageStyle,
+        column: u16,
+        row: u16,
+    ) -> Option<[u8; 3]> {
+        let width = self.left as u32 + column as u32;
+        let height = self.top as u32 + row as u32;
+        if width >= grid.width as u32 || height >= grid.height as u32 {
+            return None;
+        }
+        style.cell_color(grid, (width as u16, height as u16))
+    }
+
+    fn pixel_size(&self, style: &ImageStyle) -> (u32, u32) {
+        let cell_size = style.cell_size as u32;
+        let border = 2 * style.padding as u32 + style.grid_line_width();
+        (
+            self.columns as u32 * cell_size + border,
+            self.rows as u32 * cell_size + border,
+        )
+    }
+}
+
+// The colors of an indexed image: the background, the grid lines, then the
+// colors of the cells as they are met. Images have 256 colors at most, the
+// colors past them are drawn in the closest one.
+struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    fn new(style: &ImageStyle) -> Self {
+        Palette {
+            colors: vec![
+                style.background_color,
+                style.grid_line_color.unwrap_or(style.background_color),
+            ],
+        }
+    }
+
+    fn index(&mut self, color: [u8; 3]) -> u8 {
+        if let Some(index) = self.colors.iter().position(|known| *known == color) {
+            return index as u8;
+        }
+        if self.colors.len() < 256 {
+            self.colors.push(color);
+            return (self.colors.len() - 1) as u8;
+        }
+        let distance = |known: &[u8; 3]| -> u32 {
+            known
+                .iter()
+                .zip(color)
+                .map(|(known, channel)| (*known as i32 - channel as i32).pow(2) as u32)
+                .sum()
+        };
+        (0..self.colors.len())
+            .min_by_key(|index| distance(&self.colors[*index]))
+            .unwrap_or_default() as u8
+    }
+
+    // Red, green and blue bytes, as images write their palettes
+    fn bytes(&self) -> Vec<u8> {
+        self.colors.concat()
+    }
+}
+
+// Render the cells of a region as indices of a palette of their colors.
+// Cells outside the grid are drawn as dead cells.
+fn render_indexed(grid: &Grid, region: &Region, style: &ImageStyle) -> (Vec<u8>, Palette) {
+    let (pixel_width, pixel_height) = region.pixel_size(style);
+    let padding = style.padding as u32;
+    let cell_size = style.cell_size as u32;
+    let content_width = region.columns as u32 * cell_size + style.grid_line_width();
+    let content_height = region.rows as u32 * cell_size + style.grid_line_width();
+    let mut pixels = vec![BACKGROUND_INDEX; (pixel_width * pixel_height) as usize];
+    let mut palette = Palette::new(style);
+    // Each cell is looked up once, its pixels take its index
+    let mut cells = Vec::with_capacity(region.columns as usize * region.rows as usize);
+    for row in 0..region.rows {
+        for column in 0..region.columns {
+            let color = region.cell_color(grid, style, column, row);
+            cells.push(color.map(|color| palette.index(color)));
+        }
+    }
+
+    for (pixel_index, pixel) in pixels.iter_mut().enumerate() {
+        let pixel_index = pixel_index as u32;
+        let (x, y) = (pixel_index % pixel_width, pixel_index / pixel_width);
+        if x < padding || y < padding {
+            continue;
+        }
+        let (x, y) = (x - padding, y - padding);
+        if x >= content_width || y >= content_height {
+            continue;
+        }
+
+        if style.grid_line_color.is_some()
+            && (x.is_multiple_of(cell_size) || y.is_multiple_of(cell_size))
+        {
+            *pixel = GRID_LINE_INDEX;
+        } else if let Some(index) =
+            cells[((y / cell_size) * region.columns as u32 + x / cell_size) as usize]
+        {
+            *pixel = index;
+        }
+    }
+
+    (pixels, palette)
+}
+
+fn too_large(region: &Region) -> Error {
+    Error::Export(format!(
+        "{}x{} cells are too many for an image with this cell size",
+        region.columns, region.rows
+    ))
+}
+
+/// Parse a color written as RRGGBB, with or without a leading '#'
+pub fn parse_hex_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut color = [0_u8; 3];
+    for (index, channel) in color.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    Some(color)
+}
+
+fn hex_color(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton;
+    use crate::color::Color;
+
+    #[test]
+    fn age_shades_span_both_colors() {
+        let style = ImageStyle {
+            cell_color: [0, 0, 0],
+            old_cell_color: Some([70, 140, 210]),
+            ..ImageStyle::default()
+        };
+
+        assert_eq!(style.shade_color(style.shade(0)), [0, 0, 0]);
+        assert_eq!(style.shade_color(style.shade(AGE_SPAN * 2)), [70, 140, 210]);
+        assert!(style.shade(1) > 0, "Aging cells should change color!");
+    }
+
+    #[test]
+    fn every_state_has_its_color() {
+        // A WireWorld electron going right, with its tail behind it and its wire
+        // ahead
+        let mut grid = Grid::new(4, 1);
+        grid.set_automaton(automaton::parse("WireWorld").unwrap());
+        for (index, state) in [((0, 0), 2), ((1, 0), 1), ((2, 0), 3)] {
+            grid.set_cell(index, state);
+        }
+        let style = ImageStyle {
+            cell_size: 1,
+            ..ImageStyle::default()
+        };
+
+        let (pixels, palette) = render_indexed(&grid, &Region::whole(&grid), &style);
+
+        let colors: Vec<[u8; 3]> = pixels
+            .iter()
+            .map(|index| palette.colors[*index as usize])
+            .collect();
+        assert_eq!(
+            colors,
+            [Color::Red, Color::Blue, Color::Yellow, BACKGROUND_COLOR].map(Color::rgb)
+        );
+    }
+
+    #[test]
+    fn grid_lines_surround_every_cell() {
+        let mut grid = Grid::new(2, 1);
+        grid.cycle_cell((1, 0));
+        let style = ImageStyle {
+            cell_size: 3,
+            padding: 1,
+            grid_line_color: Some([255, 255, 255]),
+            ..ImageStyle::default()
+        };
+
+        let (pixels, _) = render_indexed(&grid, &Region::whole(&grid), &style);
+
+        // One pixel of padding surrounds the lines at 0, 3 and 6
+        let row = |y: usize| &pixels[y * 9..(y + 1) * 9];
+        assert_eq!(row(0), &[0; 9]);
+        assert_eq!(row(1), &[0, 1, 1, 1, 1, 1, 1, 1, 0]);
+        assert_eq!(row(2), &[0, 1, 0, 0, 1, 2, 2, 1, 0]);
+        assert_eq!(row(4), &[0, 1, 1, 1, 1, 1, 1, 1, 0]);
+    }
+}
@@ -0,0 +1,254 @@
+use png::BitDepth;
+use png::ColorType;
+use png::Encoder;
+use png::EncodingError;
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use super::hex_color;
+use super::render_indexed;
+use super::too_large;
+use super::ImageStyle;
+use super::Region;
+use crate::error::Error;
+use crate::grid::Grid;
+
+/// Save the cells of a region of the grid as an indexed PNG image
+pub fn save_png(
+    path: &Path,
+    grid: &Grid,
+    region: &Region,
+    style: &ImageStyle,
+) -> Result<(), Error> {
+    let (pixel_width, pixel_height) = region.pixel_size(style);
+    if pixel_width.checked_mul(pixel_height).is_none() {
+        return Err(too_large(region));
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = Encoder::new(file, pixel_width, pixel_height);
+    encoder.set_color(ColorType::Indexed);
+    encoder.set_depth(BitDepth::Eight);
+    let (pixels, palette) = render_indexed(grid, region, style);
+    encoder.set_palette(palette.bytes());
+
+    let mut writer = encoder.write_header().map_err(encoding_error)?;
+    writer.write_image_data(&pixels).map_err(encoding_error)?;
+    writer.finish().map_err(encoding_error)
+}
+
+/// Save the cells of a region of the grid as an SVG image, where neighboring
+/// cells of a color are merged into rectangles
+pub fn save_svg(
+    path: &Path,
+    grid: &Grid,
+    region: &Region,
+    style: &ImageStyle,
+) -> Result<(), Error> {
+    Ok(fs::write(path, render_svg(grid, region, style))?)
+}
+
+fn encoding_error(error: EncodingError) -> Error {
+    match error {
+        EncodingError::IoError(error) => Error::Io(error),
+        error => Error::Export(error.to_string()),
+    }
+}
+
+// A rectangle of cells of the same color, in cell coordinates of the region
+#[derive(PartialEq, Debug)]
+struct Rectangle {
+    column: u16,
+    row: u16,
+    columns: u16,
+    rows: u16,
+    color: [u8; 3],
+}
+
+fn render_svg(grid: &Grid, region: &Region, style: &ImageStyle) -> String {
+    let (pixel_width, pixel_height) = region.pixel_size(style);
+    let cell_size = style.cell_size as u32;
+    let padding = style.padding as u32;
+    let line_width = style.grid_line_width();
+
+    let mut svg = String::new();
+    // Writing into a String never fails
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{pixel_width}" height="{pixel_height}" viewBox="0 0 {pixel_width} {pixel_height}" shape-rendering="crispEdges">"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{pixel_width}" height="{pixel_height}" fill="{}"/>"#,
+        hex_color(style.background_color)
+    );
+
+    // Group the rectangles by color, so each color is written once
+    let rectangles = merge_cells(grid, region, style);
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    for rectangle in &rectangles {
+        if !colors.contains(&rectangle.color) {
+            colors.push(rectangle.color);
+        }
+    }
+    for color in colors {
+        let _ = writeln!(svg, r#"<g fill="{}">"#, hex_color(color));
+        for rectangle in rectangles
+            .iter()
+            .filter(|rectangle| rectangle.color == color)
+        {
+            // Rectangles cover the grid lines between their cells, the lines are drawn on top
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+                padding + rectangle.column as u32 * cell_size + line_width,
+                padding + rectangle.row as u32 * cell_size + line_width,
+                rectangle.columns as u32 * cell_size - line_width,
+                rectangle.rows as u32 * cell_size - line_width,
+            );
+        }
+        svg.push_str("</g>\n");
+    }
+
+    if let Some(grid_line_color) = style.grid_line_color {
+        // Lines run through the middle of their pixels to stay sharp
+        let content_width = region.columns as u32 * cell_size;
+        let content_height = region.rows as u32 * cell_size;
+        let mut path = String::new();
+        for column in 0..=region.columns as u32 {
+            let x = padding + column * cell_size;
+            let _ = write!(path, "M{x}.5 {padding}v{content_height}");
+        }
+        for row in 0..=region.rows as u32 {
+            let y = padding + row * cell_size;
+            let _ = write!(path, "M{padding} {y}.5h{content_width}");
+        }
+        let _ = writeln!(
+            svg,
+            r#"<path d="{path}" stroke="{}" stroke-width="1" fill="none"/>"#,
+            hex_color(grid_line_color)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Merge the cells that aren't dead into as few rectangles as possible: consecutive cells of a row
+// are merged into runs, and runs spanning the same columns in consecutive rows
+// are merged into one rectangle.
+fn merge_cells(grid: &Grid, region: &Region, style: &ImageStyle) -> Vec<Rectangle> {
+    let mut rectangles = Vec::new();
+    let mut open_rectangles: Vec<Rectangle> = Vec::new();
+
+    for row in 0..=region.rows {
+        let mut runs: Vec<(u16, u16, [u8; 3])> = Vec::new();
+        if row < region.rows {
+            for column in 0..region.columns {
+                let Some(color) = region.cell_color(grid, style, column, row) else {
+                    continue;
+                };
+                match runs.last_mut() {
+                    Some((start, length, run_color))
+                        if *start + *length == column && *run_color == color =>
+                    {
+                        *length += 1
+                    }
+                    _ => runs.push((column, 1, color)),
+                }
+            }
+        }
+
+        let mut continued_rectangles = Vec::new();
+        for (column, columns, color) in runs {
+            let above = open_rectangles.iter().position(|rectangle| {
+                rectangle.column == column
+                    && rectangle.columns == columns
+                    && rectangle.color == color
+            });
+            match above {
+                Some(position) => {
+                    let mut rectangle = open_rectangles.swap_remove(position);
+                    rectangle.rows += 1;
+                    continued_rectangles.push(rectangle);
+                }
+                None => continued_rectangles.push(Rectangle {
+                    column,
+                    row,
+                    columns,
+                    rows: 1,
+                    color,
+                }),
+            }
+        }
+        rectangles.append(&mut open_rectangles);
+        open_rectangles = continued_rectangles;
+    }
+
+    rectangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::CELL_COLOR;
+
+    #[test]
+    fn cells_are_merged_into_rectangles() {
+        // A 2x3 rectangle next to a lone cell
+        let mut grid = Grid::new(6, 4);
+        for cell_index in [(1, 0), (2, 0), (1, 1), (2, 1), (1, 2), (2, 2), (4, 3)] {
+            grid.cycle_cell(cell_index);
+        }
+
+        let mut rectangles = merge_cells(&grid, &Region::whole(&grid), &ImageStyle::default());
+        rectangles.sort_by_key(|rectangle| rectangle.row);
+
+        assert_eq!(
+            rectangles,
+            vec![
+                Rectangle {
+                    column: 1,
+                    row: 0,
+                    columns: 2,
+                    rows: 3,
+                    color: CELL_COLOR.rgb()
+                },
+                Rectangle {
+                    column: 4,
+                    row: 3,
+                    columns: 1,
+                    rows: 1,
+                    color: CELL_COLOR.rgb()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn svg_covers_only_the_region() {
+        let mut grid = Grid::new(10, 10);
+        grid.cycle_cell((2, 2));
+        grid.cycle_cell((8, 8));
+        let region = Region {
+            left: 2,
+            top: 2,
+            columns: 3,
+            rows: 3,
+        };
+        let style = ImageStyle {
+            cell_size: 4,
+            padding: 2,
+            ..ImageStyle::default()
+        };
+
+        let svg = render_svg(&grid, &region, &style);
+
+        assert!(svg.contains(r#"width="16" height="16""#));
+        assert!(svg.contains(r#"<rect x="2" y="2" width="4" height="4"/>"#));
+        assert_eq!(svg.matches("<rect").count(), 2);
+    }
+}
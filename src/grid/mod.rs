@@ -1,110 +1,260 @@
+use std::mem;
 use std::ops::{Index, IndexMut};
 
 mod resize;
+mod summed_area;
+mod topology;
 
-use resize::Direction;
+pub use resize::Anchor;
+pub use resize::Direction;
+pub use resize::DEFAULT_MAX_WORLD_SIZE;
+pub use summed_area::SummedAreaTable;
+pub use topology::Topology;
+
+use crate::automaton::Automaton;
+use crate::rule::Rule;
+
+/// Cell states. Generations rules add decaying states after these, and
+/// automata with several species of live cells number them from ALIVE up.
+pub const DEAD: u8 = 0;
+/// The state of live cells, and of the first species
+pub const ALIVE: u8 = 1;
 
 // Consider creating an Index struct with width and height fields
 
-// store all the rows in one vec contiguously
-//
-// ------------------------> +width
-// |
-// |
-// |
-// |
-// |
-// |
-// +
-// heigth
+/// store all the rows in one vec contiguously
+///
+/// ------------------------> +width
+/// |
+/// |
+/// |
+/// |
+/// |
+/// |
+/// +
+/// heigth
 pub struct Grid {
+    /// The number of columns
     pub width: u16,
+    /// The number of rows
     pub height: u16,
-    cells: Vec<bool>,
+    cells: Vec<u8>,
+    // The number of generations each live cell has survived
+    ages: Vec<u32>,
+    /// The number of live cells
     pub population: usize,
+    /// The number of generations played since the start
     pub generation: usize,
+    /// The number of cells that were born and that died in the last generation
+    pub births: usize,
+    /// See `births`
+    pub deaths: usize,
+    automaton: Box<dyn Automaton>,
 
-    // These flags is used to prevent resizing the terminal only from one direction
-    // when the resize amount is an odd number.
-    width_append_direction: Direction,
-    height_append_direction: Direction,
-    width_remove_direction: Direction,
-    height_remove_direction: Direction,
+    /// These flags is used to prevent resizing the terminal only from one direction
+    /// when the resize amount is an odd number.
+    pub width_append_direction: Direction,
+    /// See `width_append_direction`
+    pub height_append_direction: Direction,
+    /// See `width_append_direction`
+    pub width_remove_direction: Direction,
+    /// See `width_append_direction`
+    pub height_remove_direction: Direction,
+
+    /// Where the grid stays in place when it is resized
+    pub anchor: Anchor,
+    /// Whether the edges of the grid wrap around
+    pub topology: Topology,
+    /// Cells that don't fit when the grid is made smaller are kept up to this
+    /// size of the world around the grid
+    pub max_world_size: (u16, u16),
+    hidden: resize::Hidden,
 }
 
 impl Grid {
+    /// An empty grid of a size, playing Conway's Game of Life
     pub fn new(width: u16, height: u16) -> Self {
         Grid {
             width,
             height,
-            cells: vec![false; (width * height) as usize],
+            cells: vec![DEAD; width as usize * height as usize],
+            ages: vec![0; width as usize * height as usize],
             // Todo: Check if population or generation aren't going out of bound
             population: 0,
             generation: 0,
+            births: 0,
+            deaths: 0,
+            automaton: Box::new(Rule::default()),
             width_append_direction: Direction::Right,
             height_append_direction: Direction::Bottom,
             width_remove_direction: Direction::Right,
             height_remove_direction: Direction::Bottom,
+            anchor: Anchor::default(),
+            topology: Topology::default(),
+            max_world_size: DEFAULT_MAX_WORLD_SIZE,
+            hidden: resize::Hidden::default(),
         }
     }
 
-    pub fn next_generation(&mut self) {
-        let mut should_toggle_indices = Vec::<(u16, u16)>::new();
-
-        for width in 0..self.width {
-            for height in 0..self.height {
-                let alive_neighbors_count = self.count_alive_neighbors((width, height));
-                if self[(width, height)] {
-                    if alive_neighbors_count < 2 || alive_neighbors_count > 3 {
-                        should_toggle_indices.push((width, height));
-                    }
-                } else {
-                    if alive_neighbors_count == 3 {
-                        should_toggle_indices.push((width, height));
-                    }
+    /// What makes the cells change
+    pub fn automaton(&self) -> &dyn Automaton {
+        self.automaton.as_ref()
+    }
+
+    /// Cells in states the new automaton doesn't have are cleared
+    pub fn set_automaton(&mut self, automaton: Box<dyn Automaton>) {
+        self.automaton = automaton;
+        self.automaton.fit(self.width, self.height);
+        for height in 0..self.height {
+            for width in 0..self.width {
+                if self[(width, height)] >= self.automaton.states() {
+                    self.set_cell((width, height), DEAD);
                 }
             }
         }
+        // Which states are alive depends on the automaton
+        self.population = self.species_populations().iter().sum();
+    }
+
+    /// Live cells are in the states of the automaton's species
+    pub fn is_alive(&self, state: u8) -> bool {
+        (ALIVE..=self.automaton.species()).contains(&state)
+    }
+
+    /// The number of live cells of each species
+    pub fn species_populations(&self) -> Vec<usize> {
+        let mut populations = vec![0; self.automaton.species() as usize];
+        for state in &self.cells {
+            if self.is_alive(*state) {
+                populations[(state - ALIVE) as usize] += 1;
+            }
+        }
+        populations
+    }
+
+    /// Play one generation of the automaton
+    pub fn next_generation(&mut self) {
+        // The automaton reads the grid while it updates its own state
+        let mut automaton = mem::replace(&mut self.automaton, Box::new(Rule::default()));
+        let changed_cells = automaton.step(self);
+        self.automaton = automaton;
+
+        let species = ALIVE..=self.automaton.species();
+        for (age, state) in self.ages.iter_mut().zip(&self.cells) {
+            if species.contains(state) {
+                *age = age.saturating_add(1);
+            }
+        }
+
+        self.births = 0;
+        self.deaths = 0;
+        for (index, state) in changed_cells {
+            match (self.is_alive(self[index]), self.is_alive(state)) {
+                (_, true) => self.births += 1,
+                (true, _) => self.deaths += 1,
+                _ => {}
+            }
+            self.set_cell(index, state);
+        }
+
+        self.generation += 1;
+    }
+
+    /// Clear every cell, the ones kept outside of the grid too
+    pub fn clear(&mut self) {
+        self.cells.fill(DEAD);
+        self.ages.fill(0);
+        self.population = 0;
+        self.forget_hidden_cells();
+    }
+
+    /// Move a cell to the next state the automaton offers for editing
+    pub fn cycle_cell(&mut self, index: (u16, u16)) {
+        let state = self.automaton.clicked_state(self[index]);
+        self.set_cell(index, state);
+    }
+
+    /// Paint a cell in the state of a species, or clear it when it already is
+    pub fn paint_cell(&mut self, index: (u16, u16), state: u8) {
+        let state = if self[index] == state { DEAD } else { state };
+        self.set_cell(index, state);
+    }
+
+    /// Put a cell in a state, which resets its age when the state changes
+    pub fn set_cell(&mut self, index: (u16, u16), state: u8) {
+        // Only cells in live states count as population
+        match (self.is_alive(self[index]), self.is_alive(state)) {
+            (false, true) => self.population += 1,
+            (true, false) => self.population -= 1,
+            _ => {}
+        }
 
-        for index in should_toggle_indices {
-            self.toggle_cell(index);
+        if self[index] != state {
+            self.ages[index.1 as usize * self.width as usize + index.0 as usize] = 0;
         }
+        self[index] = state;
+    }
 
-        self.generation = self.generation + 1;
+    /// The number of generations a cell has been alive for
+    pub fn age(&self, (width, height): (u16, u16)) -> u32 {
+        self.ages[height as usize * self.width as usize + width as usize]
     }
 
-    pub fn toggle_cell(&mut self, index: (u16, u16)) {
-        self[index] = !self[index];
+    pub(crate) fn set_age(&mut self, (width, height): (u16, u16), age: u32) {
+        self.ages[height as usize * self.width as usize + width as usize] = age;
+    }
 
-        if self[index] {
-            self.population = self.population + 1;
-        } else {
-            self.population = self.population - 1;
+    /// The smallest rectangle containing all live cells as (left, top, right, bottom)
+    pub fn bounding_box(&self) -> Option<(u16, u16, u16, u16)> {
+        let mut bounding_box: Option<(u16, u16, u16, u16)> = None;
+        for height in 0..self.height {
+            for width in 0..self.width {
+                if !self.is_alive(self[(width, height)]) {
+                    continue;
+                }
+                bounding_box = Some(match bounding_box {
+                    None => (width, height, width, height),
+                    Some((left, top, right, bottom)) => {
+                        (left.min(width), top, right.max(width), bottom.max(height))
+                    }
+                });
+            }
         }
+        bounding_box
     }
 
-    fn count_alive_neighbors(&self, (width, height): (u16, u16)) -> u8 {
-        let mut alive_neighbors_count = 0_u8;
-        let neighbor_offsets: [(isize, isize); 8] = [
-            (-1, -1),
-            (-1, 0),
-            (-1, 1),
-            (0, 1),
-            (1, 1),
-            (1, 0),
-            (1, -1),
+    /// Which neighbors of the cell are alive, bit i for neighbor i clockwise from the north
+    pub fn neighborhood(&self, (width, height): (u16, u16)) -> u8 {
+        const NEIGHBOR_OFFSETS: [(isize, isize); 8] = [
             (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
         ];
-        for neighbor_offset in neighbor_offsets {
-            let neighbor_width = width as isize + neighbor_offset.0;
-            let neighbor_height = height as isize + neighbor_offset.1;
-            if neighbor_width < 0 || neighbor_height < 0 {
-                continue;
+        let mut neighborhood = 0_u8;
+        for (neighbor, neighbor_offset) in NEIGHBOR_OFFSETS.iter().enumerate() {
+            if let Some(state) = self.neighbor((width, height), *neighbor_offset) {
+                if self.is_alive(state) {
+                    neighborhood |= 1 << neighbor;
+                }
             }
-            // Todo: Check for the numeric type and make sure no overflow happens
-            if let Some(is_alive) = self.get(neighbor_width as u16, neighbor_height as u16) {
-                if *is_alive {
-                    alive_neighbors_count = alive_neighbors_count + 1;
+        }
+
+        neighborhood
+    }
+
+    /// How many neighbors of the cell are alive, at the given offsets from it
+    pub fn count_alive_neighbors(&self, (width, height): (u16, u16), offsets: &[(i16, i16)]) -> u8 {
+        let mut alive_neighbors_count = 0_u8;
+        for (column_offset, row_offset) in offsets {
+            let offset = (*column_offset as isize, *row_offset as isize);
+            if let Some(state) = self.neighbor((width, height), offset) {
+                if self.is_alive(state) {
+                    alive_neighbors_count += 1;
                 }
             }
         }
@@ -112,7 +262,33 @@ impl Grid {
         alive_neighbors_count
     }
 
-    fn get(&self, width: u16, height: u16) -> Option<&bool> {
+    /// The state of the cell at an offset from another, wrapping around the
+    /// edges of a torus. There are no cells beyond the edges of a plane.
+    pub fn neighbor(
+        &self,
+        (width, height): (u16, u16),
+        (column_offset, row_offset): (isize, isize),
+    ) -> Option<u8> {
+        let neighbor_width = width as isize + column_offset;
+        let neighbor_height = height as isize + row_offset;
+        match self.topology {
+            Topology::Plane => {
+                if neighbor_width < 0 || neighbor_height < 0 {
+                    return None;
+                }
+                self.get(neighbor_width as u16, neighbor_height as u16)
+                    .copied()
+            }
+            Topology::Torus => {
+                let neighbor_width = neighbor_width.rem_euclid(self.width as isize);
+                let neighbor_height = neighbor_height.rem_euclid(self.height as isize);
+                Some(self[(neighbor_width as u16, neighbor_height as u16)])
+            }
+        }
+    }
+
+    /// The state of a cell, None outside of the grid
+    pub fn get(&self, width: u16, height: u16) -> Option<&u8> {
         // Return None if the index is out of bound
         if width >= self.width || height >= self.height {
             return None;
@@ -127,9 +303,9 @@ impl Grid {
 }
 
 impl Index<(u16, u16)> for Grid {
-    type Output = bool;
+    type Output = u8;
 
-    fn index(self: &Self, index: (u16, u16)) -> &Self::Output {
+    fn index(&self, index: (u16, u16)) -> &Self::Output {
         let width = index.0 as usize;
         let height = index.1 as usize;
         let grid_width = self.width as usize;
@@ -138,7 +314,7 @@ impl Index<(u16, u16)> for Grid {
 }
 
 impl IndexMut<(u16, u16)> for Grid {
-    fn index_mut(self: &mut Self, index: (u16, u16)) -> &mut Self::Output {
+    fn index_mut(&mut self, index: (u16, u16)) -> &mut Self::Output {
         let width = index.0 as usize;
         let height = index.1 as usize;
         let grid_width = self.width as usize;
@@ -149,21 +325,22 @@ impl IndexMut<(u16, u16)> for Grid {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::automaton;
 
     #[test]
     fn alone_cells_should_die() {
         let mut grid = Grid::new(100, 100);
-        grid.toggle_cell((0, 0));
-        grid.toggle_cell((5, 5));
-        grid.toggle_cell((99, 99));
-        grid.toggle_cell((0, 50));
-        grid.toggle_cell((50, 0));
+        grid.cycle_cell((0, 0));
+        grid.cycle_cell((5, 5));
+        grid.cycle_cell((99, 99));
+        grid.cycle_cell((0, 50));
+        grid.cycle_cell((50, 0));
 
         grid.next_generation();
 
         assert_eq!(grid.population, 0);
         assert!(
-            grid.cells == vec![false; 10000],
+            grid.cells == vec![DEAD; 10000],
             "The grid cells are not as expected!"
         );
     }
@@ -173,14 +350,14 @@ mod tests {
         let mut grid = Grid::new(100, 100);
         let alive_cell_indices = [(0, 0), (0, 1), (5, 0), (5, 1), (98, 98), (99, 99)];
         for cell_index in alive_cell_indices {
-            grid.toggle_cell(cell_index);
+            grid.cycle_cell(cell_index);
         }
 
         grid.next_generation();
 
         assert_eq!(grid.population, 0);
         for cell_index in alive_cell_indices {
-            assert_eq!(grid[cell_index], false);
+            assert_eq!(grid[cell_index], DEAD);
         }
     }
 
@@ -190,7 +367,7 @@ mod tests {
         let mut grid = Grid::new(100, 100);
         let alive_cell_indices = [(10, 10), (10, 11), (10, 12)];
         for cell_index in alive_cell_indices {
-            grid.toggle_cell(cell_index);
+            grid.cycle_cell(cell_index);
         }
 
         grid.next_generation();
@@ -198,7 +375,101 @@ mod tests {
 
         let alive_cell_indices = [(9, 11), (10, 11), (11, 11)];
         for cell_index in alive_cell_indices {
-            assert_eq!(grid[cell_index], true);
+            assert_eq!(grid[cell_index], ALIVE);
+        }
+        assert_eq!((grid.births, grid.deaths), (2, 2));
+        assert_eq!(grid.bounding_box(), Some((9, 11, 11, 11)));
+    }
+
+    // Grids can have more cells than a u16 counts
+    #[test]
+    fn large_grids() {
+        let mut grid = Grid::new(300, 300);
+        grid.cycle_cell((299, 299));
+        assert_eq!(grid[(299, 299)], ALIVE);
+        assert_eq!(grid.population, 1);
+    }
+
+    // Brian's Brain: every live cell dies, leaving a trail of decaying cells
+    #[test]
+    fn generations_rule() {
+        let mut grid = Grid::new(10, 10);
+        grid.set_automaton(automaton::parse("/2/3").unwrap());
+        grid.cycle_cell((4, 4));
+        grid.cycle_cell((5, 4));
+
+        grid.next_generation();
+        assert_eq!(grid[(4, 4)], 2);
+        assert_eq!(grid[(4, 3)], ALIVE);
+        assert_eq!(grid.population, 4);
+        assert_eq!((grid.births, grid.deaths), (4, 2));
+
+        grid.next_generation();
+        assert_eq!(grid[(4, 4)], DEAD);
+        assert_eq!(grid[(4, 3)], 2);
+    }
+
+    #[test]
+    fn von_neumann_neighborhood() {
+        // Cells with one orthogonal neighbor are born, diagonal ones don't count
+        let mut grid = Grid::new(10, 10);
+        grid.set_automaton(automaton::parse("B1/SV").unwrap());
+        grid.cycle_cell((4, 4));
+
+        grid.next_generation();
+        assert_eq!(grid.population, 4);
+        assert_eq!(grid[(4, 3)], ALIVE);
+        assert_eq!(grid[(3, 3)], DEAD);
+    }
+
+    #[test]
+    fn larger_than_life() {
+        // Cells next to the sides of a 3x3 block see six of its cells within two
+        // steps and are born, those next to its corners only see four
+        let mut grid = Grid::new(12, 12);
+        grid.set_automaton(automaton::parse("R2,C0,M1,S5..9,B5..9,NM").unwrap());
+        for width in 5..8 {
+            for height in 5..8 {
+                grid.cycle_cell((width, height));
+            }
+        }
+
+        grid.next_generation();
+        assert_eq!(grid.bounding_box(), Some((4, 4, 8, 8)));
+        assert_eq!(grid.population, 9 + 4 * 3);
+    }
+
+    #[test]
+    fn hexagonal_neighborhood() {
+        // In the even row 4, the neighbors above and below are shifted to the left
+        let mut grid = Grid::new(10, 10);
+        grid.set_automaton(automaton::parse("B1/SH").unwrap());
+        grid.cycle_cell((4, 4));
+
+        grid.next_generation();
+        assert_eq!(grid.population, 6);
+        for cell_index in [(3, 3), (4, 3), (3, 4), (5, 4), (3, 5), (4, 5)] {
+            assert_eq!(grid[cell_index], ALIVE);
+        }
+    }
+
+    // A glider moves one cell diagonally every 4 generations, so it goes
+    // around a 5 by 5 torus in 20
+    #[test]
+    fn gliders_go_around_a_torus() {
+        let mut grid = Grid::new(5, 5);
+        grid.topology = Topology::Torus;
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        for cell_index in glider {
+            grid.cycle_cell(cell_index);
+        }
+
+        for _ in 0..20 {
+            grid.next_generation();
+            assert_eq!(grid.population, 5);
+        }
+        for cell_index in glider {
+            assert_eq!(grid[cell_index], ALIVE);
         }
     }
 }
@@ -0,0 +1,48 @@
+/// Where the grid stays in place when it is resized
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Anchor {
+    /// Rows and columns are added to and removed from both sides, the odd one
+    /// alternating between them
+    #[default]
+    Center,
+    /// Rows and columns are added and removed on the right and the bottom
+    TopLeft,
+    /// The middle of the bounding box of the live cells moves to the middle
+    BoundingBox,
+    /// The center of mass of the live cells moves to the middle
+    CenterOfMass,
+}
+
+impl Anchor {
+    /// Every anchor, in the order they are cycled through
+    pub const ALL: [Anchor; 4] = [
+        Anchor::Center,
+        Anchor::TopLeft,
+        Anchor::BoundingBox,
+        Anchor::CenterOfMass,
+    ];
+
+    /// The anchor after this one, back to the first after the last
+    pub fn next(self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|anchor| *anchor == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The name of the anchor, as the command line and sessions write it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Center => "center",
+            Self::TopLeft => "top-left",
+            Self::BoundingBox => "bounding-box",
+            Self::CenterOfMass => "center-of-mass",
+        }
+    }
+
+    /// The anchor of a name, if it is one
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|anchor| anchor.name() == name)
+    }
+}
@@ -1,12 +1,18 @@
-#[derive(PartialEq, Clone, Copy)]
+/// The side of the grid an odd column or row is added to or removed from
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Direction {
+    /// The first column
     Left,
+    /// The last column
     Right,
+    /// The first row
     Top,
+    /// The last row
     Bottom,
 }
 
 impl Direction {
+    /// Turn to the opposite side
     pub fn toggle(&mut self) {
         *self = match self {
             Self::Left => Self::Right,
@@ -15,4 +21,25 @@ impl Direction {
             Self::Bottom => Self::Top,
         };
     }
+
+    /// The name of the side, as sessions save it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+        }
+    }
+
+    /// The side of a name, if it is one
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            "top" => Some(Self::Top),
+            "bottom" => Some(Self::Bottom),
+            _ => None,
+        }
+    }
 }
@@ -1,131 +1,304 @@
+mod anchor;
 mod direction;
 
-use std::{collections::VecDeque, vec};
+use std::collections::HashMap;
 
 use super::Grid;
+use super::DEAD;
+pub use anchor::Anchor;
 pub use direction::Direction;
 
+/// Resizing keeps the cells of a world this large around the grid by default
+pub const DEFAULT_MAX_WORLD_SIZE: (u16, u16) = (4096, 4096);
+
+// A cell state is moved around together with its age
+pub(crate) type Cell = (u8, u32);
+
+/// The cells left outside of the grid when it was made smaller, so making it
+/// larger again brings them back. They are placed in the coordinates of the
+/// world, where the top left cell of the grid is at the origin.
+#[derive(Default)]
+pub(super) struct Hidden {
+    origin: (i32, i32),
+    cells: HashMap<(i32, i32), Cell>,
+}
+
 impl Grid {
-    fn row_population(row: VecDeque<bool>) -> usize {
-        let mut population = 0_usize;
+    /// Change the size of the grid. Cells that don't fit any more are kept
+    /// outside of it, within the maximum size of the world, and come back
+    /// where they were when the grid grows again.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        let (left, top) = self.growth_offsets(width, height);
 
-        for cell in row {
-            if cell {
-                population = population + 1;
+        // Hide every cell, then bring back the ones within the new grid
+        let (origin_width, origin_height) = self.hidden.origin;
+        let cells = self.cells.iter().zip(&self.ages);
+        for (index, (&state, &age)) in cells.enumerate() {
+            if state != DEAD {
+                let world_width = (index % self.width as usize) as i32 + origin_width;
+                let world_height = (index / self.width as usize) as i32 + origin_height;
+                self.hidden
+                    .cells
+                    .insert((world_width, world_height), (state, age));
             }
         }
+        let origin = (origin_width - left, origin_height - top);
+        let states = self.automaton.states();
+        let size = width as usize * height as usize;
+        self.cells = vec![DEAD; size];
+        self.ages = vec![0; size];
+        for index in 0..size {
+            let world_width = (index % width as usize) as i32 + origin.0;
+            let world_height = (index / width as usize) as i32 + origin.1;
+            if let Some((state, age)) = self.hidden.cells.remove(&(world_width, world_height)) {
+                // States of an automaton the cell was hidden under may be gone
+                if state < states {
+                    (self.cells[index], self.ages[index]) = (state, age);
+                }
+            }
+        }
+        self.hidden.origin = origin;
+        self.width = width;
+        self.height = height;
 
-        population
+        // Forget the cells that are too far out
+        let margin_width = self.max_world_size.0.saturating_sub(width) as i32 / 2;
+        let margin_height = self.max_world_size.1.saturating_sub(height) as i32 / 2;
+        let world_widths = origin.0 - margin_width..origin.0 + width as i32 + margin_width;
+        let world_heights = origin.1 - margin_height..origin.1 + height as i32 + margin_height;
+        self.hidden.cells.retain(|(world_width, world_height), _| {
+            world_widths.contains(world_width) && world_heights.contains(world_height)
+        });
+
+        self.population = self.species_populations().iter().sum();
+        self.automaton.fit(width, height);
     }
-    // TODO: If the dimentions are lower than a limit, a message should be shown
-    pub fn resize(&mut self, width: u16, height: u16) {
-        // Todo: Check if anythinig goes out of bound in isize type
-        let mut width_difference: isize = width as isize - self.width as isize;
-        let mut height_difference: isize = height as isize - self.height as isize;
-        let width_difference_half = width_difference.abs() / 2;
-        let height_difference_half = height_difference.abs() / 2;
 
-        // Decompose grid into rows
-        // NOTE: The rows order is reversed after this conversion and should be reversed again at the end
-        let mut rows: VecDeque<VecDeque<bool>> = VecDeque::new();
-        for _ in 0..self.height {
-            let row = self.cells.split_off(self.cells.len() - self.width as usize);
-            rows.push_back(VecDeque::from(row));
-        }
+    /// The number of cells kept outside of the grid
+    pub fn hidden_population(&self) -> usize {
+        self.hidden.cells.len()
+    }
 
-        // Adjust the grid to the new height
-        if height_difference > 0 {
-            let new_row: VecDeque<bool> = VecDeque::from(vec![false; self.width as usize]);
-            // Add half of the new rows on the top of the grid and half on the bottom
-            // TODO: check if we can get rid of clones and make it more efficient
-            for _ in 0..height_difference_half {
-                rows.push_front(new_row.clone());
-                rows.push_back(new_row.clone());
+    /// The cells kept outside of the grid with their state and age, placed
+    /// from the top left cell of the grid, row by row
+    pub(crate) fn hidden_cells(&self) -> Vec<((i32, i32), Cell)> {
+        let (origin_width, origin_height) = self.hidden.origin;
+        let mut cells: Vec<_> = self
+            .hidden
+            .cells
+            .iter()
+            .map(|((width, height), cell)| ((width - origin_width, height - origin_height), *cell))
+            .collect();
+        cells.sort_unstable_by_key(|((width, height), _)| (*height, *width));
+        cells
+    }
+
+    /// Keep a cell outside of the grid, placed from its top left cell
+    pub(crate) fn hide_cell(&mut self, (width, height): (i32, i32), cell: Cell) {
+        let (origin_width, origin_height) = self.hidden.origin;
+        self.hidden
+            .cells
+            .insert((width + origin_width, height + origin_height), cell);
+    }
+
+    pub(super) fn forget_hidden_cells(&mut self) {
+        self.hidden.cells.clear();
+    }
+
+    // How many columns and rows are added on the left and the top of the grid,
+    // negative when they are removed, as the anchor wants. Anchors on the live
+    // cells center the grid on them, or on its middle when there are none.
+    fn growth_offsets(&mut self, width: u16, height: u16) -> (i32, i32) {
+        let middle = match self.anchor {
+            Anchor::Center => None,
+            Anchor::TopLeft => return (0, 0),
+            Anchor::BoundingBox => self.bounding_box().map(|(left, top, right, bottom)| {
+                (
+                    (left as f64 + right as f64) / 2.0,
+                    (top as f64 + bottom as f64) / 2.0,
+                )
+            }),
+            Anchor::CenterOfMass => self.center_of_mass(),
+        };
+        let Some((middle_width, middle_height)) = middle else {
+            return self.centered_offsets(width, height);
+        };
+
+        // A side that keeps its size doesn't move
+        let offset = |old: u16, new: u16, middle: f64| {
+            if old == new {
+                0
+            } else {
+                ((new as f64 - 1.0) / 2.0 - middle).round() as i32
+            }
+        };
+        (
+            offset(self.width, width, middle_width),
+            offset(self.height, height, middle_height),
+        )
+    }
+
+    // The mean place of the live cells
+    fn center_of_mass(&self) -> Option<(f64, f64)> {
+        if self.population == 0 {
+            return None;
+        }
+        let (mut widths, mut heights) = (0.0, 0.0);
+        for height in 0..self.height {
+            for width in 0..self.width {
+                if self.is_alive(self[(width, height)]) {
+                    widths += width as f64;
+                    heights += height as f64;
+                }
             }
+        }
+        let population = self.population as f64;
+        Some((widths / population, heights / population))
+    }
 
-            // Add the extra row in case of the height_difference is an odd number
+    // Half of the difference is added to or removed from each side, and an odd
+    // one alternates between the sides
+    fn centered_offsets(&mut self, width: u16, height: u16) -> (i32, i32) {
+        let width_difference = width as i32 - self.width as i32;
+        let height_difference = height as i32 - self.height as i32;
+        let width_difference_half = width_difference.abs() / 2;
+        let height_difference_half = height_difference.abs() / 2;
+
+        let mut top = 0;
+        if height_difference > 0 {
+            top = height_difference_half;
             if height_difference % 2 != 0 {
                 if self.height_append_direction == Direction::Top {
-                    rows.push_back(new_row.clone());
-                } else if self.height_append_direction == Direction::Bottom {
-                    rows.push_front(new_row.clone());
+                    top += 1;
                 }
                 self.height_remove_direction = self.height_append_direction;
                 self.height_append_direction.toggle();
             }
         }
-
         if height_difference < 0 {
-            height_difference = height_difference * -1;
-            for _ in 0..height_difference_half {
-                // TODO: Check if you should propagate the error or unwrap is fine
-                self.population = self.population
-                    - Self::row_population(rows.pop_front().unwrap())
-                    - Self::row_population(rows.pop_back().unwrap());
-            }
-
-            // TODO: Check if you should propagate the error or unwrap is fine
+            top = -height_difference_half;
             if height_difference % 2 != 0 {
                 if self.height_remove_direction == Direction::Top {
-                    self.population =
-                        self.population - Self::row_population(rows.pop_back().unwrap());
-                }
-                if self.height_remove_direction == Direction::Bottom {
-                    self.population =
-                        self.population - Self::row_population(rows.pop_front().unwrap());
+                    top -= 1;
                 }
                 self.height_append_direction = self.height_remove_direction;
                 self.height_remove_direction.toggle();
             }
         }
 
-        self.height = height;
-
-        // Adjust the grid to the new width
+        let mut left = 0;
         if width_difference > 0 {
-            for row in rows.iter_mut() {
-                for _ in 0..width_difference_half {
-                    row.push_front(false);
-                    row.push_back(false);
-                }
-
-                if width_difference % 2 != 0 {
-                    if self.width_append_direction == Direction::Right {
-                        row.push_back(false);
-                    }
-                    if self.width_append_direction == Direction::Left {
-                        row.push_front(false);
-                    }
-                }
+            left = width_difference_half;
+            if width_difference % 2 != 0 && self.width_append_direction == Direction::Left {
+                left += 1;
             }
-
             self.width_remove_direction = self.width_append_direction;
             self.width_append_direction.toggle();
         }
-
         if width_difference < 0 {
-            for row in rows.iter_mut() {
-                for _ in 0..width_difference_half {
-                    self.population = self.population - row.pop_front().unwrap() as usize;
-                    self.population = self.population - row.pop_back().unwrap() as usize;
+            left = -width_difference_half;
+            if width_difference % 2 != 0 {
+                if self.width_remove_direction == Direction::Left {
+                    left -= 1;
                 }
-                if width_difference % 2 != 0 {
-                    if self.width_remove_direction == Direction::Right {
-                        self.population = self.population - row.pop_back().unwrap() as usize;
-                    } else if self.width_remove_direction == Direction::Left {
-                        self.population = self.population - row.pop_front().unwrap() as usize;
-                    }
+                self.width_append_direction = self.width_remove_direction;
+                self.width_remove_direction.toggle();
+            }
+        }
+
+        (left, top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::ALIVE;
+
+    fn cells(grid: &Grid) -> Vec<(u16, u16)> {
+        let mut cells = Vec::new();
+        for height in 0..grid.height {
+            for width in 0..grid.width {
+                if grid[(width, height)] != DEAD {
+                    cells.push((width, height));
                 }
             }
+        }
+        cells
+    }
 
-            if width_difference % 2 != 0 {
-                self.width_append_direction = self.width_remove_direction;
-                self.width_remove_direction.toggle();
+    #[test]
+    fn shrinking_and_growing_back_is_lossless() {
+        let mut grid = Grid::new(10, 8);
+        let corners = [(0, 0), (9, 0), (4, 4), (0, 7), (9, 7)];
+        for cell_index in corners {
+            grid.cycle_cell(cell_index);
+        }
+
+        for (width, height) in [(7, 5), (4, 2), (12, 3), (1, 1)] {
+            grid.resize(width, height);
+            assert_eq!(grid.population + grid.hidden_population(), corners.len());
+        }
+        grid.resize(10, 8);
+        assert_eq!(cells(&grid), corners);
+        assert_eq!(grid.population, corners.len());
+
+        // The cells of the grid are kept around it as it grows further
+        grid.resize(14, 10);
+        grid.resize(10, 8);
+        assert_eq!(cells(&grid), corners);
+    }
+
+    // Where the cell at (2, 2) of a 6x6 grid, the corner of an L spanning 2 to
+    // 4 and 2 to 3, goes when the grid grows or shrinks by a difference
+    fn moved_cell(anchor: Anchor, difference: i32) -> Option<(u16, u16)> {
+        let mut grid = Grid::new(6, 6);
+        grid.anchor = anchor;
+        for cell_index in [(2, 2), (3, 2), (4, 2), (2, 3)] {
+            grid.cycle_cell(cell_index);
+        }
+        let size = (6 + difference) as u16;
+        grid.resize(size, size);
+        (0..size)
+            .flat_map(|height| (0..size).map(move |width| (width, height)))
+            .find(|index| grid[*index] == ALIVE)
+    }
+
+    #[test]
+    fn anchors_place_the_grid() {
+        let expectations = [
+            // The odd row and column first go to the right and the bottom
+            (Anchor::Center, [(4, 4), (3, 3), (1, 1), (1, 1)]),
+            (Anchor::TopLeft, [(2, 2), (2, 2), (2, 2), (2, 2)]),
+            // The middle of the L is at (3, 2.5), which moves to the middle
+            (Anchor::BoundingBox, [(4, 4), (3, 4), (0, 1), (0, 0)]),
+            // Its center of mass is at (2.75, 2.25)
+            (Anchor::CenterOfMass, [(4, 4), (3, 4), (1, 1), (0, 1)]),
+        ];
+        for (anchor, cells) in expectations {
+            for (difference, cell) in [4, 3, -2, -3].into_iter().zip(cells) {
+                assert_eq!(
+                    moved_cell(anchor, difference),
+                    Some(cell),
+                    "{} anchor, difference {difference}",
+                    anchor.name()
+                );
             }
         }
+    }
 
-        // TODO: Check if this operation is efficient
-        self.cells = rows.into_iter().rev().flatten().collect();
-        self.width = width;
+    #[test]
+    fn cells_beyond_the_world_are_forgotten() {
+        let mut grid = Grid::new(10, 10);
+        grid.max_world_size = (6, 10);
+        grid.cycle_cell((0, 5));
+        grid.cycle_cell((5, 5));
+
+        // The world reaches one column past each side of the 4 remaining ones
+        grid.resize(4, 10);
+        assert_eq!((grid.population, grid.hidden_population()), (1, 0));
+        grid.resize(10, 10);
+        assert_eq!(grid[(5, 5)], ALIVE);
+        assert_eq!(grid.population, 1);
     }
 }
@@ -0,0 +1,107 @@
+use super::Grid;
+use super::Topology;
+
+/// The number of live cells above and to the left of every corner between cells,
+/// so the live cells of any rectangle are counted with four lookups instead of
+/// visiting every cell of it.
+/// <https://en.wikipedia.org/wiki/Summed-area_table>
+pub struct SummedAreaTable {
+    // One more column and row than the grid, for the corners on its edges
+    columns: usize,
+    rows: usize,
+    sums: Vec<u32>,
+    // Rectangles wrap around the edges of a torus
+    wraps: bool,
+}
+
+impl SummedAreaTable {
+    /// Sum the live cells of a grid
+    pub fn new(grid: &Grid) -> Self {
+        let columns = grid.width as usize + 1;
+        let rows = grid.height as usize + 1;
+        let mut sums = vec![0; columns * rows];
+        for row in 1..rows {
+            let mut row_sum = 0;
+            for column in 1..columns {
+                row_sum += grid.is_alive(grid[(column as u16 - 1, row as u16 - 1)]) as u32;
+                sums[row * columns + column] = sums[(row - 1) * columns + column] + row_sum;
+            }
+        }
+        SummedAreaTable {
+            columns,
+            rows,
+            sums,
+            wraps: grid.topology == Topology::Torus,
+        }
+    }
+
+    /// The live cells from (left, top) to (right, bottom) included. The part of
+    /// the rectangle outside the grid is empty, or wraps around a torus.
+    pub fn sum(&self, left: isize, top: isize, right: isize, bottom: isize) -> u32 {
+        if !self.wraps {
+            return self.clamped_sum(left, top, right, bottom);
+        }
+        let columns = wrapped(left, right, self.columns as isize - 1);
+        let rows = wrapped(top, bottom, self.rows as isize - 1);
+        let mut sum = 0;
+        for (left, right) in &columns {
+            for (top, bottom) in &rows {
+                sum += self.clamped_sum(*left, *top, *right, *bottom);
+            }
+        }
+        sum
+    }
+
+    fn clamped_sum(&self, left: isize, top: isize, right: isize, bottom: isize) -> u32 {
+        let clamp_column = |column: isize| column.clamp(0, self.columns as isize - 1) as usize;
+        let clamp_row = |row: isize| row.clamp(0, self.rows as isize - 1) as usize;
+        let (left, right) = (clamp_column(left), clamp_column(right + 1));
+        let (top, bottom) = (clamp_row(top), clamp_row(bottom + 1));
+        if left >= right || top >= bottom {
+            return 0;
+        }
+
+        let corner = |column: usize, row: usize| self.sums[row * self.columns + column];
+        corner(right, bottom) + corner(left, top) - corner(left, bottom) - corner(right, top)
+    }
+}
+
+// The pieces of the grid a range of columns or rows covers when it wraps
+// around, each time it goes around counting again
+fn wrapped(mut start: isize, end: isize, size: isize) -> Vec<(isize, isize)> {
+    let mut pieces = Vec::new();
+    while start <= end {
+        let offset = start.div_euclid(size) * size;
+        let piece_end = end.min(offset + size - 1);
+        pieces.push((start - offset, piece_end - offset));
+        start = piece_end + 1;
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rectangles_are_counted() {
+        let mut grid = Grid::new(5, 4);
+        for cell_index in [(0, 0), (1, 1), (4, 3), (2, 1)] {
+            grid.cycle_cell(cell_index);
+        }
+        let table = SummedAreaTable::new(&grid);
+
+        assert_eq!(table.sum(0, 0, 4, 3), 4);
+        assert_eq!(table.sum(1, 1, 2, 1), 2);
+        assert_eq!(table.sum(-3, -3, 0, 0), 1);
+        assert_eq!(table.sum(4, 3, 10, 10), 1);
+        assert_eq!(table.sum(6, 0, 9, 3), 0);
+
+        // On a torus, (-1, -1) is the bottom right cell
+        grid.topology = Topology::Torus;
+        let table = SummedAreaTable::new(&grid);
+        assert_eq!(table.sum(-1, -1, 0, 0), 2);
+        // Ten columns go around the five of the grid twice
+        assert_eq!(table.sum(0, 0, 9, 3), 8);
+    }
+}
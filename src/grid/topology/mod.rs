@@ -0,0 +1,32 @@
+/// How the edges of the grid meet. The torus wraps the neighbors the rules
+/// count, and the automata with edges of their own keep them: elementary
+/// automata wrap with their :T suffix, block rules and ants stop at the edges.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Topology {
+    /// Cells beyond the edges are dead
+    #[default]
+    Plane,
+    /// The left edge meets the right one and the top edge the bottom one, so
+    /// patterns that leave on a side come back on the other
+    Torus,
+}
+
+impl Topology {
+    /// Every topology
+    pub const ALL: [Topology; 2] = [Topology::Plane, Topology::Torus];
+
+    /// The name of the topology, as the command line and sessions write it
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Plane => "plane",
+            Self::Torus => "torus",
+        }
+    }
+
+    /// The topology of a name, if it is one
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|topology| topology.name() == name)
+    }
+}
@@ -0,0 +1,55 @@
+//! Cellular automata on a finite grid: Life-like rules and their many
+//! extensions (Generations, isotropic non-totalistic, Larger than Life, other
+//! neighborhoods), and other automata such as WireWorld, Langton's ant,
+//! elementary and block automata. Patterns are read and written as RLE and
+//! Macrocell files, apgcodes and saved sessions, and exported as images.
+//! Frontends draw their screen through a [`render::Renderer`].
+//!
+//! ```
+//! use game_of_life::{automaton, Grid};
+//!
+//! let mut grid = Grid::new(10, 10);
+//! grid.set_automaton(automaton::parse("B36/S23").unwrap());
+//! for cell in [(4, 3), (4, 4), (4, 5)] {
+//!     grid.cycle_cell(cell);
+//! }
+//! grid.next_generation();
+//! assert_eq!(grid.population, 3);
+//! ```
+//!
+//! The terminal interface is the binary of the crate, built with the `tui`
+//! feature.
+
+#![warn(missing_docs)]
+
+/// Names of still lifes, oscillators and spaceships, and the objects they name
+pub mod apgcode;
+/// What makes cells change: Life-like rules and the other automata
+pub mod automaton;
+/// The colors of cells and of the screen
+pub mod color;
+/// The errors of the library
+pub mod error;
+/// Grids as PNG, SVG and animated GIF images
+pub mod export;
+/// The grid of cells, its resizes and its edges
+pub mod grid;
+/// Patterns in the Macrocell format of Golly
+pub mod macrocell;
+/// Random numbers that a seed repeats
+pub mod random;
+/// The screens frontends draw on
+pub mod render;
+/// Patterns in the run length encoded format
+pub mod rle;
+/// Life-like rules and their extensions
+pub mod rule;
+/// Games saved on quit and resumed on start
+pub mod session;
+/// The population of every generation
+pub mod statistics;
+
+pub use automaton::Automaton;
+pub use error::Error;
+pub use grid::Grid;
+pub use rule::Rule;
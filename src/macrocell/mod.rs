@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::automaton;
+use crate::automaton::Automaton;
+use crate::error::Error;
+use crate::export::Region;
+use crate::grid::Grid;
+use crate::grid::ALIVE;
+
+// Leaves are 8x8 blocks of cells, at level 3 of the tree
+const LEAF_LEVEL: u8 = 3;
+const LEAF_SIZE: u64 = 1 << LEAF_LEVEL;
+
+// A node of the quadtree. Nodes refer to their children by their number in the
+// file, starting at 1, and 0 is an empty node of any size.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Node {
+    // The rows of the block from the top, bit i of a row for its column i
+    Leaf([u8; LEAF_SIZE as usize]),
+    // A square of 2^level cells made of four squares of half its size: the
+    // north west, north east, south west and south east ones
+    Inner { level: u8, children: [usize; 4] },
+}
+
+impl Node {
+    fn level(&self) -> u8 {
+        match self {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::Inner { level, .. } => *level,
+        }
+    }
+}
+
+/// A pattern in Golly's Macrocell format: a quadtree whose identical squares
+/// are only written once, which holds huge engineered patterns in small files.
+///
+/// \[M2\] (golly 2.0)
+/// #R B3/S23
+/// $$..*$...*$.***$
+/// 4 1 0 0 0
+///
+/// Leaves list their rows, with '.' for dead cells and '*' for live ones, each
+/// ending with '$'. Other nodes give their level and their four children, and
+/// the last node is the whole pattern.
+/// <https://golly.sourceforge.io/Help/formats.html#mc>
+pub struct Macrocell {
+    /// The rule of the #R line
+    pub rule: Option<Box<dyn Automaton>>,
+    nodes: Vec<Node>,
+}
+
+impl Macrocell {
+    /// Replace the cells of the grid with the pattern, in its middle. Whatever
+    /// doesn't fit in the grid is left out.
+    pub fn place(&self, grid: &mut Grid) {
+        grid.clear();
+        let boxes = self.bounding_boxes();
+        let Some(Some((left, top, right, bottom))) = boxes.last() else {
+            return;
+        };
+
+        let column = (grid.width as i128 - (right - left + 1) as i128) / 2 - *left as i128;
+        let row = (grid.height as i128 - (bottom - top + 1) as i128) / 2 - *top as i128;
+        self.place_node(grid, self.nodes.len(), (column, row));
+    }
+
+    // Place a node with its top left corner at a (column, row) of the grid
+    fn place_node(&self, grid: &mut Grid, number: usize, (column, row): (i128, i128)) {
+        let Some(node) = number.checked_sub(1).map(|index| self.nodes[index]) else {
+            return;
+        };
+        let size = 1_i128 << node.level();
+        if column + size <= 0
+            || row + size <= 0
+            || column >= grid.width as i128
+            || row >= grid.height as i128
+        {
+            return;
+        }
+
+        match node {
+            Node::Leaf(rows) => {
+                for (row_offset, cells) in rows.iter().enumerate() {
+                    for column_offset in (0..LEAF_SIZE).filter(|bit| cells & 1 << bit != 0) {
+                        let width = column + column_offset as i128;
+                        let height = row + row_offset as i128;
+                        if (0..grid.width as i128).contains(&width)
+                            && (0..grid.height as i128).contains(&height)
+                        {
+                            grid.set_cell((width as u16, height as u16), ALIVE);
+                        }
+                    }
+                }
+            }
+            Node::Inner { children, .. } => {
+                let half = size / 2;
+                for (child, (right, down)) in
+                    children.into_iter().zip([(0, 0), (1, 0), (0, 1), (1, 1)])
+                {
+                    self.place_node(grid, child, (column + right * half, row + down * half));
+                }
+            }
+        }
+    }
+
+    // The smallest rectangle containing the live cells of each node, as
+    // (left, top, right, bottom) from its top left corner
+    fn bounding_boxes(&self) -> Vec<Option<(u64, u64, u64, u64)>> {
+        let mut boxes: Vec<Option<(u64, u64, u64, u64)>> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let mut bounding_box: Option<(u64, u64, u64, u64)> = None;
+            let mut include = |(left, top, right, bottom): (u64, u64, u64, u64)| {
+                bounding_box = Some(match bounding_box {
+                    None => (left, top, right, bottom),
+                    Some(other) => (
+                        other.0.min(left),
+                        other.1.min(top),
+                        other.2.max(right),
+                        other.3.max(bottom),
+                    ),
+                });
+            };
+
+            match node {
+                Node::Leaf(rows) => {
+                    for (row, cells) in (0..).zip(rows) {
+                        if *cells != 0 {
+                            let left = cells.trailing_zeros() as u64;
+                            let right = 7 - cells.leading_zeros() as u64;
+                            include((left, row, right, row));
+                        }
+                    }
+                }
+                Node::Inner { level, children } => {
+                    let half = 1_u64 << (level - 1);
+                    for (child, (right, down)) in
+                        children.iter().zip([(0, 0), (1, 0), (0, 1), (1, 1)])
+                    {
+                        if let Some(Some((left, top, child_right, bottom))) =
+                            child.checked_sub(1).map(|index| boxes[index])
+                        {
+                            let (column, row) = (right * half, down * half);
+                            include((column + left, row + top, column + child_right, row + bottom));
+                        }
+                    }
+                }
+            }
+            boxes.push(bounding_box);
+        }
+        boxes
+    }
+}
+
+impl FromStr for Macrocell {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = |line: usize, reason: &str| {
+            Error::InvalidPattern(format!(
+                "invalid macrocell file, line {}: {reason}",
+                line + 1
+            ))
+        };
+
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.starts_with("[M2]") => {}
+            _ => return Err(invalid(0, "expected the [M2] header")),
+        }
+
+        let mut macrocell = Macrocell {
+            rule: None,
+            nodes: Vec::new(),
+        };
+        for (line_number, line) in lines {
+            let line = line.trim();
+            if let Some(rule) = line.strip_prefix("#R") {
+                macrocell.rule = Some(automaton::parse(rule.trim())?);
+                continue;
+            }
+
+            let node = match line.chars().next() {
+                None | Some('#') => continue,
+                Some('.' | '*' | '$') => {
+                    let mut rows = [0_u8; LEAF_SIZE as usize];
+                    for (row, cells) in line.split('$').enumerate() {
+                        if cells.len() > LEAF_SIZE as usize
+                            || (row >= rows.len() && !cells.is_empty())
+                        {
+                            return Err(invalid(line_number, "leaves are 8 by 8 cells"));
+                        }
+                        for (column, cell) in cells.chars().enumerate() {
+                            match cell {
+                                '*' => rows[row] |= 1 << column,
+                                '.' => {}
+                                _ => {
+                                    return Err(invalid(
+                                        line_number,
+                                        "unexpected character in a leaf",
+                                    ))
+                                }
+                            }
+                        }
+                    }
+                    Node::Leaf(rows)
+                }
+                Some(_) => {
+                    let numbers: Vec<usize> = line
+                        .split_whitespace()
+                        .map(|number| number.parse().ok())
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| {
+                            invalid(line_number, "expected a level and four children")
+                        })?;
+                    let [level, north_west, north_east, south_west, south_east] = numbers[..]
+                    else {
+                        return Err(invalid(line_number, "expected a level and four children"));
+                    };
+                    if level == 1 {
+                        return Err(invalid(
+                            line_number,
+                            "only two-state patterns are supported",
+                        ));
+                    }
+                    if !(LEAF_LEVEL as usize + 1..64).contains(&level) {
+                        return Err(invalid(line_number, "levels are from 4 to 63"));
+                    }
+                    // Children come before their parent, one level below it
+                    let is_child = |child: usize| {
+                        child == 0
+                            || macrocell
+                                .nodes
+                                .get(child - 1)
+                                .is_some_and(|node| node.level() as usize == level - 1)
+                    };
+                    let children = [north_west, north_east, south_west, south_east];
+                    if !children.iter().all(|child| is_child(*child)) {
+                        return Err(invalid(
+                            line_number,
+                            "children are earlier nodes one level below",
+                        ));
+                    }
+                    Node::Inner {
+                        level: level as u8,
+                        children,
+                    }
+                }
+            };
+            macrocell.nodes.push(node);
+        }
+        Ok(macrocell)
+    }
+}
+
+/// Read a pattern from a Macrocell file
+pub fn load(path: &Path) -> Result<Macrocell, Error> {
+    fs::read_to_string(path)?.parse()
+}
+
+/// Write a region of the grid in the Macrocell format
+pub fn save(path: &Path, grid: &Grid, region: &Region) -> Result<(), Error> {
+    Ok(fs::write(path, write(grid, region)?)?)
+}
+
+fn write(grid: &Grid, region: &Region) -> Result<String, Error> {
+    if grid.automaton().states() > 2 {
+        return Err(Error::Export(
+            "macrocell files only hold patterns of two-state rules".to_string(),
+        ));
+    }
+
+    let mut text = format!("[M2] (game-of-life)\n#R {}\n", grid.automaton().name());
+    let size = (region.columns.max(region.rows) as u64)
+        .max(LEAF_SIZE)
+        .next_power_of_two();
+    let mut writer = Writer {
+        grid,
+        region,
+        numbers: HashMap::new(),
+        text: &mut text,
+    };
+    writer.node(size.trailing_zeros() as u8, 0, 0);
+    Ok(text)
+}
+
+// Writes the nodes of a region, each distinct node once
+struct Writer<'a> {
+    grid: &'a Grid,
+    region: &'a Region,
+    numbers: HashMap<Node, usize>,
+    text: &'a mut String,
+}
+
+impl Writer<'_> {
+    // Write the node of the square at a (column, row) of the region, and the
+    // nodes under it, returning its number
+    fn node(&mut self, level: u8, column: u64, row: u64) -> usize {
+        let node = if level == LEAF_LEVEL {
+            let mut rows = [0_u8; LEAF_SIZE as usize];
+            for (row_offset, cells) in (0..).zip(rows.iter_mut()) {
+                for column_offset in 0..LEAF_SIZE {
+                    if self.is_alive(column + column_offset, row + row_offset) {
+                        *cells |= 1 << column_offset;
+                    }
+                }
+            }
+            Node::Leaf(rows)
+        } else {
+            let half = 1 << (level - 1);
+            let children = [(0, 0), (half, 0), (0, half), (half, half)]
+                .map(|(right, down)| self.node(level - 1, column + right, row + down));
+            Node::Inner { level, children }
+        };
+
+        let is_empty = match node {
+            Node::Leaf(rows) => rows == [0; LEAF_SIZE as usize],
+            Node::Inner { children, .. } => children == [0; 4],
+        };
+        if is_empty {
+            return 0;
+        }
+        if let Some(number) = self.numbers.get(&node) {
+            return *number;
+        }
+
+        // Writing into a String never fails
+        let _ = match node {
+            Node::Leaf(rows) => {
+                let last_row = rows
+                    .iter()
+                    .rposition(|cells| *cells != 0)
+                    .unwrap_or_default();
+                for cells in &rows[..=last_row] {
+                    let width = LEAF_SIZE as u32 - cells.leading_zeros();
+                    for bit in 0..width {
+                        self.text
+                            .push(if cells & 1 << bit != 0 { '*' } else { '.' });
+                    }
+                    self.text.push('$');
+                }
+                writeln!(self.text)
+            }
+            Node::Inner { level, children } => {
+                let [north_west, north_east, south_west, south_east] = children;
+                writeln!(
+                    self.text,
+                    "{level} {north_west} {north_east} {south_west} {south_east}"
+                )
+            }
+        };
+        let number = self.numbers.len() + 1;
+        self.numbers.insert(node, number);
+        number
+    }
+
+    fn is_alive(&self, column: u64, row: u64) -> bool {
+        if column >= self.region.columns as u64 || row >= self.region.rows as u64 {
+            return false;
+        }
+        let width = self.region.left as u64 + column;
+        let height = self.region.top as u64 + row;
+        if width >= self.grid.width as u64 || height >= self.grid.height as u64 {
+            return false;
+        }
+        self.grid[(width as u16, height as u16)] == ALIVE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(grid: &Grid) -> Vec<u8> {
+        (0..grid.height)
+            .flat_map(|height| (0..grid.width).map(move |width| grid[(width, height)]))
+            .collect()
+    }
+
+    #[test]
+    fn golly_files_are_read() {
+        let macrocell: Macrocell = "[M2] (golly 2.0)\n#R B36/S23\n$$..*$...*$.***$\n4 1 0 0 1\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            macrocell.rule.as_ref().map(|rule| rule.name()),
+            Some("B36/S23".to_string())
+        );
+
+        // Two gliders, 8 cells apart, in the middle of the grid
+        let mut grid = Grid::new(16, 16);
+        grid.cycle_cell((0, 0));
+        macrocell.place(&mut grid);
+        assert_eq!(grid.population, 10);
+        assert_eq!(grid.bounding_box(), Some((2, 2, 12, 12)));
+
+        for text in [
+            "#R B3/S23\n",
+            "[M2]\n1 0 0 0 1\n",
+            "[M2]\n.*$\n5 1 0 0 0\n",
+            "[M2]\n.x$\n",
+        ] {
+            assert!(
+                text.parse::<Macrocell>().is_err(),
+                "{text:?} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn written_patterns_read_back() {
+        let mut grid = Grid::new(20, 11);
+        for index in [
+            (3, 2),
+            (4, 3),
+            (2, 4),
+            (3, 4),
+            (4, 4),
+            (15, 8),
+            (16, 8),
+            (17, 8),
+        ] {
+            grid.cycle_cell(index);
+        }
+
+        let text = write(&grid, &Region::whole(&grid)).unwrap();
+        assert!(text.starts_with("[M2] (game-of-life)\n#R B3/S23\n"));
+        let mut read_grid = Grid::new(20, 11);
+        text.parse::<Macrocell>().unwrap().place(&mut read_grid);
+        assert_eq!(cells(&read_grid), cells(&grid));
+    }
+}
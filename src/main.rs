@@ -1,13 +1,40 @@
+mod cast;
+use cast::CastRecorder;
+use cast::RecordedOutput;
+
+mod cli;
+use cli::Command;
+use cli::PlayOptions;
+
+mod exit;
+use exit::Error;
+
 mod printer;
-use printer::{print_generation, print_population, print_speed};
+use printer::Terminal;
+use printer::TerminalGuard;
+
+mod app;
+use app::keymap;
+use app::keymap::Key;
+use app::keymap::KeyMap;
+use app::script;
+use app::script::ScriptRecorder;
+use app::App;
+use app::Input;
+use app::MIN_COLUMNS;
+use app::MIN_ROWS;
+use app::VERTICAL_MARGIN;
 
-mod grid;
-use grid::Grid;
+use game_of_life::render::Renderer;
+use game_of_life::session;
+use game_of_life::session::Session;
+use game_of_life::Grid;
 
 use crossterm::cursor;
 use crossterm::event::poll;
 use crossterm::event::read;
 use crossterm::event::DisableMouseCapture;
+use crossterm::event::EnableBracketedPaste;
 use crossterm::event::EnableMouseCapture;
 use crossterm::event::Event;
 use crossterm::event::KeyCode;
@@ -17,170 +44,321 @@ use crossterm::event::MouseEventKind;
 use crossterm::execute;
 use crossterm::queue;
 use crossterm::style::Color;
-use crossterm::style::Print;
 use crossterm::style::SetBackgroundColor;
 use crossterm::terminal;
-use crossterm::terminal::disable_raw_mode;
-use crossterm::terminal::enable_raw_mode;
 use crossterm::terminal::Clear;
 use crossterm::terminal::ClearType;
 use crossterm::terminal::EnterAlternateScreen;
-use crossterm::terminal::LeaveAlternateScreen;
 use std::io;
 use std::io::stdout;
+use std::io::Stdout;
 use std::io::Write;
+use std::process::ExitCode;
 use std::time::Duration;
 use std::time::Instant;
 
-use crate::printer::print_cells;
-use crate::printer::print_ribbon_bottom;
-use crate::printer::print_ribbon_top;
+use crate::printer::grid_width;
 
-fn main() {
-    match run() {
-        Ok(()) => (),
+fn main() -> ExitCode {
+    printer::restore_terminal_on_panic();
+    let result = match cli::parse(std::env::args().skip(1)) {
+        Ok(Command::Play(options)) => run(options),
+        Ok(Command::Replay { input, speed }) => cast::replay(&input, speed),
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        Ok(Command::Gif(export)) => export.run(),
+        Ok(Command::Snapshot(export)) => export.run(),
+        Ok(Command::Apgcode { session }) => cli::print_apgcode(session),
+        Err(error) => Err(error),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
         Err(error) => {
             eprintln!("{error}");
+            error.exit_code()
         }
     }
 }
 
-fn run() -> Result<(), std::io::Error> {
+fn run(options: PlayOptions) -> Result<(), Error> {
     // Constants
-    const CELL_COLOR: Color = Color::Yellow;
     const BACKGROUND_COLOR: Color = Color::Black;
-    const TOP_MARGIN: u16 = 2;
-    const BOTTOM_MARGIN: u16 = 1;
-    const VERTICAL_MARGIN: u16 = TOP_MARGIN + BOTTOM_MARGIN;
 
-    // Global variables
-    let mut terminal_width: u16 = terminal::size().unwrap().0;
-    let mut terminal_height: u16 = terminal::size().unwrap().1;
-    let mut game_is_paused = true;
-    let mut delay: u8 = 50;
+    // A script plays on a screen of its own size
+    let script = options.script;
+    let (terminal_width, terminal_height) = match &script {
+        Some(script) => (script.columns, script.rows),
+        None => terminal::size().map_err(Error::Terminal)?,
+    };
+
+    // Offer to resume the session that was saved when the game was last quit,
+    // unless the game is scripted: scripts start from an empty grid, and leave
+    // the saved session alone
+    let is_scripted = script.is_some() || options.save_script.is_some();
+    let session_path = session::default_path().filter(|_| !is_scripted);
+    let resumed_session = match &session_path {
+        Some(path) if path.exists() && ask_to_resume()? => match session::load(path) {
+            Ok(session) => Some(session),
+            Err(error) => {
+                eprintln!("Could not resume the previous session: {error}");
+                None
+            }
+        },
+        _ => None,
+    };
+
+    // The key map of the configuration directory is used unless one is given,
+    // scripted games keep the one of their script, or the default one, so they
+    // play the same everywhere
+    let script_keymap = script.as_ref().and_then(|script| script.keymap.clone());
+    let keymap = match (options.keymap.or(script_keymap), keymap::default_path()) {
+        (Some(keymap), _) => keymap,
+        (None, Some(path)) if !is_scripted && path.exists() => KeyMap::load(&path)?,
+        (None, _) => KeyMap::default(),
+    };
+
+    // The grid is never smaller than what the game can be played in, a smaller
+    // terminal shows that it is too small until it grows
+    let grid_columns = terminal_width.max(MIN_COLUMNS);
+    let grid_rows = terminal_height.max(MIN_ROWS) - VERTICAL_MARGIN;
 
     // Create a grid to represent the terminal sheet
-    let mut grid = Grid::new(terminal_width, terminal_height - VERTICAL_MARGIN);
+    let (mut grid, game_is_paused, delay) = match resumed_session {
+        Some(Session {
+            grid,
+            delay,
+            game_is_paused,
+        }) => (grid, game_is_paused, delay),
+        None => (Grid::new(grid_columns, grid_rows), true, 50),
+    };
 
-    // ToDo
-    let mut stdout = stdout();
+    // A rule given on the command line replaces the rule of the script, of the
+    // session, and of the pattern
+    let (script_inputs, script_rule, script_pattern) = match script {
+        Some(script) => (Some(script.inputs), script.rule, script.pattern),
+        None => (None, None, None),
+    };
+    let mut pattern = options.pattern;
+    let pattern_rule = pattern.as_mut().and_then(|pattern| pattern.rule.take());
+    if let Some(rule) = options.rule.or(script_rule).or(pattern_rule) {
+        grid.set_automaton(rule);
+    }
+    if let Some(anchor) = options.anchor {
+        grid.anchor = anchor;
+    }
+    if let Some(max_world_size) = options.max_world_size {
+        grid.max_world_size = max_world_size;
+    }
+    grid.resize(grid_width(grid_columns, grid.automaton()), grid_rows);
+    // A script starts from its own cells, unless a pattern is given
+    if let Some(script_pattern) = &script_pattern {
+        script_pattern.place(&mut grid);
+    }
+    if let Some(pattern) = &pattern {
+        pattern.place(&mut grid);
+    }
+    if let Some(initial_row) = &options.initial_row {
+        initial_row.apply(&mut grid);
+    }
 
-    // Configure terminal settings for optimal display and usage
-    enable_raw_mode()?;
+    // The inputs of the game are written to a script when asked to
+    let script_recorder = match &options.save_script {
+        Some(path) => Some(ScriptRecorder::create(
+            path,
+            (terminal_width, terminal_height),
+            &grid,
+            &keymap,
+        )?),
+        None => None,
+    };
+
+    // Everything printed to the terminal is also recorded when asked to
+    let cast_recorder = match options.cast {
+        Some(path) => Some(CastRecorder::create(
+            &path,
+            terminal_width,
+            terminal_height,
+        )?),
+        None => None,
+    };
+    let mut renderer = Terminal::new(RecordedOutput::new(stdout(), cast_recorder));
+
+    // Configure terminal settings for optimal display and usage. The guard
+    // restores them however the game ends.
+    let guard = TerminalGuard::enter().map_err(Error::Terminal)?;
     queue!(
-        stdout,
+        renderer.output(),
         EnterAlternateScreen,
         SetBackgroundColor(BACKGROUND_COLOR),
         Clear(ClearType::All),
         EnableMouseCapture,
+        EnableBracketedPaste,
         cursor::Hide
-    )?;
-    stdout.flush()?;
-
-    // Print help ribbon at bottom of pane
-    print_ribbon_bottom(&mut stdout, terminal_height)?;
-
-    // Print top ribbon
-    print_ribbon_top(&mut stdout, grid.generation, delay, grid.population)?;
-
-    // TODO: Comment
-    let mut start = Instant::now();
-    loop {
-        // Read an event
-        if poll(Duration::from_millis(5)).unwrap() {
-            match read().unwrap() {
-                Event::Key(key_event) => match (key_event.code, key_event.modifiers) {
-                    (KeyCode::Char('c'), KeyModifiers::CONTROL)
-                    | (KeyCode::Char('q'), KeyModifiers::NONE) => {
-                        break;
-                    }
-                    (KeyCode::Char('p'), KeyModifiers::NONE) => {
-                        if game_is_paused {
-                            execute!(stdout, DisableMouseCapture)?;
-                        } else {
-                            execute!(stdout, EnableMouseCapture)?;
-                        }
-                        game_is_paused = !game_is_paused;
-                    }
-                    (KeyCode::Char('+'), KeyModifiers::NONE) => {
-                        if delay > 0 {
-                            delay -= 1;
-                            print_speed(&mut stdout, delay)?;
-                        }
-                    }
-                    (KeyCode::Char('-'), KeyModifiers::NONE) => {
-                        if delay < 99 {
-                            delay += 1;
-                            print_speed(&mut stdout, delay)?;
-                        }
-                    }
-                    _ => {}
-                },
-                Event::Mouse(mouse_event) => match mouse_event.kind {
-                    MouseEventKind::Down(MouseButton::Left) => {
-                        let height = mouse_event.row;
-                        if !(height < TOP_MARGIN || height >= terminal_height - BOTTOM_MARGIN) {
-                            let width = mouse_event.column;
-
-                            if grid[(width, height - TOP_MARGIN)] {
-                                grid.toggle_cell((width, height - TOP_MARGIN));
-                                queue!(stdout, SetBackgroundColor(BACKGROUND_COLOR))?;
-                            } else {
-                                grid.toggle_cell((width, height - TOP_MARGIN));
-                                queue!(stdout, SetBackgroundColor(CELL_COLOR))?;
-                            }
-                            queue!(stdout, cursor::MoveTo(width, height), Print(' '),)?;
-                            stdout.flush()?;
-                            print_population(&mut stdout, grid.population)?;
-                        }
+    )
+    .map_err(Error::Terminal)?;
+    renderer.output().flush().map_err(Error::Terminal)?;
+
+    // Mouse editing is only available while the game is paused
+    if !game_is_paused {
+        execute!(renderer.output(), DisableMouseCapture).map_err(Error::Terminal)?;
+    }
+
+    let mut app = App::new(
+        grid,
+        delay,
+        game_is_paused,
+        (terminal_width, terminal_height),
+    );
+    app.keymap = keymap;
+    let result = match script_inputs {
+        Some(inputs) => replay_script(&inputs, &mut app, &mut renderer),
+        None => play(&mut app, &mut renderer, script_recorder),
+    };
+    let restored = guard.restore().map_err(Error::Terminal);
+
+    // Save the session so it can be resumed on the next start, before any
+    // error of the game so the game isn't lost with it
+    let (session, finished) = app.finish();
+    let saved = match session_path {
+        Some(path) => session::save(&path, &session).map_err(Error::from),
+        None => Ok(()),
+    };
+    result?;
+    restored?;
+    finished?;
+    saved?;
+
+    // Finish the asciicast after the terminal has been restored
+    if let Some(cast_recorder) = renderer.output().recorder.take() {
+        cast_recorder.finish()?;
+    }
+    Ok(())
+}
+
+// Play with the keyboard and the mouse of the terminal
+fn play(
+    app: &mut App,
+    renderer: &mut Terminal<RecordedOutput<Stdout>>,
+    mut script_recorder: Option<ScriptRecorder>,
+) -> Result<(), Error> {
+    let start = Instant::now();
+    app.draw(renderer, &app.start())?;
+    while !app.has_quit() {
+        if poll(Duration::from_millis(5)).map_err(Error::Terminal)? {
+            if let Some(input) = input(read().map_err(Error::Terminal)?) {
+                let now = start.elapsed();
+                // A generation that is due comes before the input, as it does
+                // when the script of the game is replayed
+                let draws = app.tick(now)?;
+                app.draw(renderer, &draws)?;
+                let game_was_paused = app.game_is_paused;
+                if let Input::Resize { columns, rows } = input {
+                    if let Some(cast_recorder) = &mut renderer.output().recorder {
+                        cast_recorder.resize(columns, rows)?;
                     }
-                    _ => {}
-                },
-                Event::Resize(columns, rows) => {
-                    terminal_width = terminal::size().unwrap().0;
-                    terminal_height = terminal::size().unwrap().1;
-                    grid.resize(columns, rows - VERTICAL_MARGIN);
-                    if game_is_paused {
-                        print_cells(&mut stdout, &grid)?;
-                        print_ribbon_top(&mut stdout, grid.generation, delay, grid.population)?;
-                        print_ribbon_bottom(&mut stdout, terminal_height)?;
+                    renderer.resize(columns, rows).map_err(Error::terminal)?;
+                }
+                let draws = app.handle(input.clone(), now)?;
+                app.draw(renderer, &draws)?;
+                if let Some(script_recorder) = &mut script_recorder {
+                    // The seed :random drew is typed before the command is
+                    // entered, so that the script replays the same cells
+                    if let Some(seed) = app.take_drawn_seed() {
+                        script_recorder.record(now, &seed)?;
                     }
+                    script_recorder.record(now, &input)?;
                 }
-                _ => {}
-            }
-        }
 
-        // Check if game is paused
-        if game_is_paused || start.elapsed() < Duration::from_millis(8 * (delay as u64) + 250) {
-            continue;
+                // Mouse editing is only available while the game is paused
+                if app.game_is_paused && !game_was_paused {
+                    execute!(renderer.output(), EnableMouseCapture).map_err(Error::Terminal)?;
+                } else if !app.game_is_paused && game_was_paused {
+                    execute!(renderer.output(), DisableMouseCapture).map_err(Error::Terminal)?;
+                }
+            }
         }
 
-        // Generate next generation grid
-        grid.next_generation();
+        let draws = app.tick(start.elapsed())?;
+        app.draw(renderer, &draws)?;
+    }
 
-        // Print cells
-        print_cells(&mut stdout, &grid)?;
+    if let Some(script_recorder) = script_recorder {
+        script_recorder.finish()?;
+    }
+    Ok(())
+}
 
-        // Print top ribbon
-        print_generation(&mut stdout, grid.generation)?;
-        print_population(&mut stdout, grid.population)?;
+// Replay a script as it was played, until it ends or q or Ctrl+C is pressed
+fn replay_script(
+    inputs: &[(Duration, Input)],
+    app: &mut App,
+    renderer: &mut Terminal<RecordedOutput<Stdout>>,
+) -> Result<(), Error> {
+    let start = Instant::now();
+    script::play(inputs, app, renderer, |time| {
+        while start.elapsed() < time {
+            let timeout = (time - start.elapsed()).min(Duration::from_millis(5));
+            if poll(timeout).map_err(Error::Terminal)? {
+                let stops = [Input::Key(Key::from('q')), Input::Interrupt];
+                let event = read().map_err(Error::Terminal)?;
+                if input(event).is_some_and(|input| stops.contains(&input)) {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    })
+}
 
-        // Reset the instant
-        start = Instant::now();
+// The input of the game a terminal event is, if any
+fn input(event: Event) -> Option<Input> {
+    match event {
+        Event::Key(key_event) => {
+            let modifiers = key_event.modifiers;
+            if (key_event.code, modifiers) == (KeyCode::Char('c'), KeyModifiers::CONTROL) {
+                return Some(Input::Interrupt);
+            }
+            // Shift is part of the character
+            let code = match key_event.code {
+                KeyCode::Char(character) => keymap::KeyCode::Char(character),
+                KeyCode::Enter => keymap::KeyCode::Enter,
+                KeyCode::Esc => keymap::KeyCode::Escape,
+                KeyCode::Backspace => keymap::KeyCode::Backspace,
+                KeyCode::Tab => keymap::KeyCode::Tab,
+                KeyCode::Up => keymap::KeyCode::Up,
+                KeyCode::Down => keymap::KeyCode::Down,
+                KeyCode::Left => keymap::KeyCode::Left,
+                KeyCode::Right => keymap::KeyCode::Right,
+                _ => return None,
+            };
+            Some(Input::Key(Key {
+                code,
+                control: modifiers.contains(KeyModifiers::CONTROL),
+                alt: modifiers.contains(KeyModifiers::ALT),
+            }))
+        }
+        Event::Mouse(mouse_event) => match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => Some(Input::Click {
+                column: mouse_event.column,
+                row: mouse_event.row,
+            }),
+            _ => None,
+        },
+        Event::Paste(text) => Some(Input::Paste(text)),
+        Event::Resize(columns, rows) => Some(Input::Resize { columns, rows }),
+        _ => None,
     }
-    quit(stdout)?;
-    Ok(())
 }
 
-fn quit(mut stdout: io::Stdout) -> Result<(), io::Error> {
-    // Restore terminal settings to default
-    disable_raw_mode()?;
-    queue!(
-        stdout,
-        DisableMouseCapture,
-        cursor::Show,
-        LeaveAlternateScreen
-    )?;
-    stdout.flush()?;
-    Ok(())
+fn ask_to_resume() -> Result<bool, io::Error> {
+    print!("Resume previous session? [Y/n] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(answer.is_empty() || answer == "y" || answer == "yes")
 }
@@ -1,104 +1,380 @@
-use crossterm::cursor;
-use crossterm::queue;
-use crossterm::style::Color;
-use crossterm::style::Print;
-use crossterm::style::SetBackgroundColor;
-use crossterm::terminal::{Clear, ClearType};
-use std::fmt::Display;
-use std::io;
-use std::io::Stdout;
-use std::io::Write;
-
-use crate::grid::Grid;
-
-// Todo: These constants should be stored in one place and be synced
-const BACKGROUND_COLOR: Color = Color::Black;
-const CELL_COLOR: Color = Color::Yellow;
-const TOP_MARGIN: u16 = 2;
-
-// ToDo: check if it's a good idea to constrain generation to
-// unsigned integers
-pub fn print_generation<T: Display>(stdout: &mut Stdout, generation: T) -> Result<(), io::Error> {
-    queue!(
-        stdout,
-        cursor::MoveTo(0, 0),
-        SetBackgroundColor(BACKGROUND_COLOR),
-        Print(format!("{:<25}", format!("Generation: {:<13}", generation))),
-    )?;
-    stdout.flush()?;
+mod terminal;
+pub use terminal::restore_terminal_on_panic;
+pub use terminal::Terminal;
+pub use terminal::TerminalGuard;
+
+use game_of_life::automaton::Automaton;
+use game_of_life::grid::Grid;
+use game_of_life::grid::ALIVE;
+use game_of_life::render::Renderer;
+use game_of_life::render::ScreenCell;
+use game_of_life::render::Span;
+use game_of_life::statistics::Statistics;
+use game_of_life::Error;
+
+use crate::app::keymap::Action;
+use crate::app::keymap::KeyMap;
+use crate::app::BOTTOM_MARGIN;
+use crate::app::TOP_MARGIN;
+
+// The statistics panel covers this many columns on the right of the cells
+pub const PANEL_WIDTH: u16 = 30;
+const CHART_HEIGHT: usize = 8;
+
+pub fn print_cells(renderer: &mut impl Renderer, grid: &Grid) -> Result<(), Error> {
+    let mut cells = Vec::with_capacity(grid.width as usize * grid.height as usize);
+    for width in 0..grid.width {
+        for height in 0..grid.height {
+            cells.push(screen_cell(grid, (width, height)));
+        }
+    }
+    renderer.draw_cells(&cells)?;
+    renderer.present()?;
+
     Ok(())
 }
 
-pub fn print_population<T: Display>(stdout: &mut Stdout, population: T) -> Result<(), io::Error> {
-    queue!(
-        stdout,
-        cursor::MoveTo(0, 1),
-        SetBackgroundColor(BACKGROUND_COLOR),
-        Print(format!("{:<25}", format!("Population: {:<13}", population))),
-    )?;
-    stdout.flush()?;
+pub fn print_cell(
+    renderer: &mut impl Renderer,
+    grid: &Grid,
+    index: (u16, u16),
+) -> Result<(), Error> {
+    renderer.draw_cells(&[screen_cell(grid, index)])?;
+    renderer.present()?;
     Ok(())
 }
 
-pub fn print_speed(stdout: &mut Stdout, delay: u8) -> Result<(), io::Error> {
-    queue!(
-        stdout,
-        cursor::MoveTo(26, 0),
-        SetBackgroundColor(BACKGROUND_COLOR),
-        Print(format!("Speed: {:<10}", 100 - delay)),
-    )?;
-    stdout.flush()?;
+fn screen_cell(grid: &Grid, index: (u16, u16)) -> ScreenCell {
+    ScreenCell {
+        column: cell_column(grid.automaton(), index),
+        row: index.1 + TOP_MARGIN,
+        width: if grid.automaton().is_staggered() {
+            2
+        } else {
+            1
+        },
+        color: grid.automaton().color(index, grid[index]),
+    }
+}
+
+// Hexagonal cells are two columns wide, and odd rows are shifted by one column
+// so every cell sits between its neighbors in the rows above and below
+fn cell_column(automaton: &dyn Automaton, (width, height): (u16, u16)) -> u16 {
+    if automaton.is_staggered() {
+        2 * width + height % 2
+    } else {
+        width
+    }
+}
+
+// How many cells of a row fit in the terminal
+pub fn grid_width(terminal_width: u16, automaton: &dyn Automaton) -> u16 {
+    if automaton.is_staggered() {
+        terminal_width.saturating_sub(1) / 2
+    } else {
+        terminal_width
+    }
+}
+
+// The cell drawn at a column of a grid row, if any
+pub fn cell_at(grid: &Grid, column: u16, height: u16) -> Option<(u16, u16)> {
+    let width = if grid.automaton().is_staggered() {
+        column.checked_sub(height % 2)? / 2
+    } else {
+        column
+    };
+    (width < grid.width && height < grid.height).then_some((width, height))
+}
+
+// Print the statistics panel over the right side of the cells
+pub fn print_statistics(
+    renderer: &mut impl Renderer,
+    statistics: &Statistics,
+    grid: &Grid,
+    keymap: &KeyMap,
+    terminal_width: u16,
+) -> Result<(), Error> {
+    let left = terminal_width.saturating_sub(PANEL_WIDTH);
+    // Leave room for the border and a space
+    let content_width = PANEL_WIDTH.min(terminal_width).saturating_sub(2) as usize;
+    let mut lines = statistics.panel_lines(grid, content_width, CHART_HEIGHT);
+    // The history is saved with the key the key map binds, or as a command
+    let key = keymap
+        .bindings(&Action::ExportCsv)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| ":csv".to_string());
+    let hint = format!("{key}: save history as CSV");
+    lines.extend([String::new(), hint.chars().take(content_width).collect()]);
+    for row in 0..grid.height {
+        let line = lines.get(row as usize).map_or("", String::as_str);
+        renderer.draw_overlay(left, row + TOP_MARGIN, &format!("│ {line:<content_width$}"))?;
+    }
+    renderer.present()?;
+
     Ok(())
 }
 
-pub fn print_cells(stdout: &mut Stdout, grid: &Grid) -> Result<(), io::Error> {
-    for width in 0..grid.width {
-        for height in 0..grid.height {
-            if grid[(width, height)] {
-                queue!(stdout, SetBackgroundColor(CELL_COLOR))?;
-            } else {
-                queue!(stdout, SetBackgroundColor(BACKGROUND_COLOR))?;
-            }
-            queue!(
-                stdout,
-                cursor::MoveTo(width, height + TOP_MARGIN),
-                Print(' '),
-            )?;
+// The first keys of the main actions, as many as fit, and the key of the help,
+// which lists the others
+pub fn print_ribbon_bottom(
+    renderer: &mut impl Renderer,
+    keymap: &KeyMap,
+    (columns, rows): (u16, u16),
+) -> Result<(), Error> {
+    let key = |action: Action| keymap.bindings(&action).into_iter().next();
+    let mut items = Vec::new();
+    for (action, name) in [(Action::Quit, "quit"), (Action::Pause, "pause")] {
+        items.extend(key(action).map(|key| format!("{key}: {name}")));
+    }
+    match (key(Action::Faster), key(Action::Slower)) {
+        (Some(faster), Some(slower)) if faster.chars().count() + slower.chars().count() == 2 => {
+            items.push(format!("speed: {faster}{slower}"))
         }
+        (Some(faster), Some(slower)) => items.push(format!("speed: {faster}/{slower}")),
+        _ => {}
     }
+    let actions = [
+        (Action::Record, "record"),
+        (Action::Snapshot, "snapshot"),
+        (Action::Statistics, "statistics"),
+    ];
+    for (action, name) in actions {
+        items.extend(key(action).map(|key| format!("{key}: {name}")));
+    }
+    let help = key(Action::Help).map(|key| format!("{key}: help"));
 
-    stdout.flush()?;
+    const SEPARATOR: &str = "    ";
+    let room = (columns as usize).saturating_sub(
+        help.as_ref()
+            .map_or(0, |help| SEPARATOR.len() + help.chars().count()),
+    );
+    let mut ribbon = String::new();
+    for item in items {
+        let width = ribbon.chars().count() + SEPARATOR.len() + item.chars().count();
+        if !ribbon.is_empty() && width > room {
+            break;
+        }
+        if !ribbon.is_empty() {
+            ribbon.push_str(SEPARATOR);
+        }
+        ribbon.push_str(&item);
+    }
+    if let Some(help) = help {
+        if !ribbon.is_empty() {
+            ribbon.push_str(SEPARATOR);
+        }
+        ribbon.push_str(&help);
+    }
+    print_message(renderer, rows, &ribbon)
+}
+
+// A message shown in place of the bottom ribbon, until the next key press
+pub fn print_message(
+    renderer: &mut impl Renderer,
+    terminal_height: u16,
+    message: &str,
+) -> Result<(), Error> {
+    renderer.draw_status(terminal_height - 1, &[Span::new(0, message)])?;
+    renderer.present()?;
 
     Ok(())
 }
 
-pub fn print_ribbon_bottom(stdout: &mut Stdout, terminal_height: u16) -> Result<(), io::Error> {
-    queue!(
-        stdout,
-        cursor::MoveTo(0, terminal_height - 1),
-        Clear(ClearType::CurrentLine),
-        Print("q: quit    p: pause    speed: +-"),
-    )?;
-    stdout.flush()?;
+// The actions and their keys in a box over the middle of the cells, cut at
+// the bottom ribbon
+pub fn print_help(
+    renderer: &mut impl Renderer,
+    lines: &[(String, String)],
+    (columns, rows): (u16, u16),
+) -> Result<(), Error> {
+    let description_width = lines
+        .iter()
+        .map(|(description, _)| description.chars().count())
+        .max()
+        .unwrap_or_default();
+    let mut text: Vec<String> = lines
+        .iter()
+        .map(|(description, keys)| format!(" {description:<description_width$}   {keys} "))
+        .collect();
+    text.insert(0, " Keys".to_string());
+    let width = text
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    let left = columns.saturating_sub(width as u16) / 2;
+    let bottom = rows.saturating_sub(BOTTOM_MARGIN);
+    let rows = (TOP_MARGIN..bottom).zip(&text);
+    for (row, line) in rows {
+        renderer.draw_overlay(left, row, &format!("{line:<width$}"))?;
+    }
+    renderer.present()?;
+
+    Ok(())
+}
+
+// In place of the game, in the middle of the screen
+pub fn print_too_small(
+    renderer: &mut impl Renderer,
+    (columns, rows): (u16, u16),
+    (min_columns, min_rows): (u16, u16),
+) -> Result<(), Error> {
+    let lines = [
+        "Terminal too small".to_string(),
+        format!("need {min_columns}×{min_rows}"),
+    ];
+    let top = rows.saturating_sub(lines.len() as u16) / 2;
+    for row in 0..rows {
+        let spans: Vec<Span> = row
+            .checked_sub(top)
+            .and_then(|line| lines.get(line as usize))
+            .map(|line| {
+                let column = columns.saturating_sub(line.chars().count() as u16) / 2;
+                Span::new(column, line.as_str())
+            })
+            .into_iter()
+            .collect();
+        renderer.draw_status(row, &spans)?;
+    }
+    renderer.present()?;
 
     Ok(())
 }
 
+// The first row shows the generation, the speed and the rule, the second one
+// the population and what is specific to the game or the automaton. Automata
+// with several species show the species clicks paint and count each species,
+// random ones show their seed.
 pub fn print_ribbon_top(
-    stdout: &mut Stdout,
-    generation: usize,
+    renderer: &mut impl Renderer,
+    grid: &Grid,
     delay: u8,
-    population: usize,
-) -> Result<(), io::Error> {
-    queue!(
-        stdout,
-        cursor::MoveToRow(0),
-        Clear(ClearType::CurrentLine),
-        cursor::MoveToRow(1),
-        Clear(ClearType::CurrentLine)
+    is_recording: bool,
+    species: u8,
+) -> Result<(), Error> {
+    let automaton = grid.automaton();
+    renderer.draw_status(
+        0,
+        &[
+            Span::new(0, format!("Generation: {}", grid.generation)),
+            Span::new(26, format!("Speed: {}", 100 - delay)),
+            Span::new(44, format!("Rule: {}", automaton.name())),
+        ],
     )?;
-    print_generation(stdout, generation)?;
-    print_speed(stdout, delay)?;
-    print_population(stdout, population)?;
+
+    let mut spans = vec![Span::new(0, format!("Population: {}", grid.population))];
+    if is_recording {
+        spans.push(Span::new(26, "Recording"));
+    }
+    if automaton.species() > 1 {
+        spans.push(Span::new(36, format!("Paint: {species}")));
+        spans.push(Span::colored(45, "■", automaton.color((0, 0), species)));
+        let mut column = 48;
+        for (species, population) in (ALIVE..).zip(grid.species_populations()) {
+            spans.push(Span::colored(column, "■", automaton.color((0, 0), species)));
+            spans.push(Span::new(column + 2, population.to_string()));
+            column += 8;
+        }
+    }
+    if let Some(seed) = automaton.seed() {
+        spans.push(Span::new(44, format!("Seed: {seed}")));
+    }
+    renderer.draw_status(1, &spans)?;
+    renderer.present()?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_of_life::automaton;
+    use game_of_life::color::CELL_COLOR;
+    use game_of_life::render::Memory;
+
+    #[test]
+    fn screens_are_drawn_through_the_renderer() {
+        let mut grid = Grid::new(12, 3);
+        for cell_index in [(1, 1), (2, 1), (3, 1)] {
+            grid.cycle_cell(cell_index);
+        }
+        let mut screen = Memory::new(80, 6);
+        print_cells(&mut screen, &grid).unwrap();
+        print_ribbon_top(&mut screen, &grid, 50, true, ALIVE).unwrap();
+        print_ribbon_bottom(&mut screen, &KeyMap::default(), (80, 6)).unwrap();
+
+        assert_eq!(
+            screen.text(),
+            [
+                "Generation: 0             Speed: 50         Rule: B3/S23",
+                "Population: 3             Recording",
+                "",
+                " ###",
+                "",
+                "q: quit    p: pause    speed: +-    r: record    s: snapshot    ?: help",
+            ]
+            .join("\n")
+        );
+        assert_eq!(screen.background(1, 3), Some(CELL_COLOR));
+        assert_eq!(screen.presented, 3);
+
+        // Species are painted in their colors
+        grid.set_automaton(automaton::parse("quadlife").unwrap());
+        print_ribbon_top(&mut screen, &grid, 50, false, 2).unwrap();
+        assert_eq!(
+            screen.line(1),
+            "Population: 3                       Paint: 2 ■  ■ 3     ■ 0     ■ 0     ■ 0"
+        );
+        assert_eq!(
+            screen.foreground(45, 1),
+            Some(grid.automaton().color((0, 0), 2))
+        );
+    }
+
+    #[test]
+    fn keys_are_shown_as_the_key_map_binds_them() {
+        let keymap: KeyMap = "pause = space, ctrl+p\nfaster = ctrl+up\nrecord ="
+            .parse()
+            .unwrap();
+        let mut screen = Memory::new(40, 8);
+        print_ribbon_bottom(&mut screen, &keymap, (40, 8)).unwrap();
+        assert_eq!(screen.line(7), "q: quit    space: pause    ?: help");
+
+        let lines = [
+            ("Quit".to_string(), "q".to_string()),
+            ("Pause or resume".to_string(), "space, ctrl+p".to_string()),
+            ("Start or stop recording".to_string(), "unbound".to_string()),
+        ];
+        print_help(&mut screen, &lines, (40, 8)).unwrap();
+        assert_eq!(
+            screen.text(),
+            [
+                "",
+                "",
+                " Keys",
+                " Quit                      q",
+                " Pause or resume           space, ctrl+p",
+                " Start or stop recording   unbound",
+                "",
+                "q: quit    space: pause    ?: help",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn the_statistics_panel_shows_the_key_of_the_csv() {
+        let grid = Grid::new(80, 27);
+        let statistics = Statistics::new(&grid);
+        let mut screen = Memory::new(80, 30);
+        let keymap: KeyMap = "csv = ctrl+s".parse().unwrap();
+        print_statistics(&mut screen, &statistics, &grid, &keymap, 80).unwrap();
+        assert!(screen.text().contains("│ ctrl+s: save history as CSV"));
+
+        let keymap: KeyMap = "csv =".parse().unwrap();
+        print_statistics(&mut screen, &statistics, &grid, &keymap, 80).unwrap();
+        assert!(screen.text().contains("│ :csv: save history as CSV"));
+    }
+}
@@ -0,0 +1,182 @@
+use crossterm::cursor;
+use crossterm::event::DisableBracketedPaste;
+use crossterm::event::DisableMouseCapture;
+use crossterm::execute;
+use crossterm::queue;
+use crossterm::style::Color;
+use crossterm::style::Print;
+use crossterm::style::ResetColor;
+use crossterm::style::SetBackgroundColor;
+use crossterm::style::SetForegroundColor;
+use crossterm::terminal::disable_raw_mode;
+use crossterm::terminal::enable_raw_mode;
+use crossterm::terminal::LeaveAlternateScreen;
+use crossterm::terminal::{Clear, ClearType};
+use std::io;
+use std::io::Write;
+use std::panic;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use game_of_life::color;
+use game_of_life::render::Renderer;
+use game_of_life::render::ScreenCell;
+use game_of_life::render::Span;
+use game_of_life::Error;
+
+const BACKGROUND_COLOR: Color = terminal_color(color::BACKGROUND_COLOR);
+
+// Whether the terminal is in raw mode and has to be restored
+static TERMINAL_IS_SET_UP: AtomicBool = AtomicBool::new(false);
+
+// Draws the screen on a terminal with crossterm commands, written to the output
+// when presented
+pub struct Terminal<W: Write> {
+    output: W,
+}
+
+impl<W: Write> Terminal<W> {
+    pub fn new(output: W) -> Self {
+        Terminal { output }
+    }
+
+    // For the commands that set the terminal up, such as mouse capture
+    pub fn output(&mut self) -> &mut W {
+        &mut self.output
+    }
+}
+
+impl<W: Write> Renderer for Terminal<W> {
+    fn draw_cells(&mut self, cells: &[ScreenCell]) -> Result<(), Error> {
+        for cell in cells {
+            queue!(
+                self.output,
+                SetBackgroundColor(terminal_color(cell.color)),
+                cursor::MoveTo(cell.column, cell.row),
+                Print(" ".repeat(cell.width as usize)),
+            )?;
+        }
+        Ok(())
+    }
+
+    // The spans are written first and the rest of the row cleared after them,
+    // so the line doesn't flicker when it is redrawn
+    fn draw_status(&mut self, row: u16, spans: &[Span]) -> Result<(), Error> {
+        queue!(
+            self.output,
+            cursor::MoveTo(0, row),
+            SetBackgroundColor(BACKGROUND_COLOR)
+        )?;
+        let mut column = 0;
+        for span in spans {
+            let gap = span.column.saturating_sub(column) as usize;
+            queue!(self.output, Print(" ".repeat(gap)))?;
+            match span.color {
+                Some(color) => queue!(
+                    self.output,
+                    SetForegroundColor(terminal_color(color)),
+                    Print(&span.text),
+                    ResetColor,
+                    SetBackgroundColor(BACKGROUND_COLOR),
+                )?,
+                None => queue!(self.output, Print(&span.text))?,
+            }
+            column = column.max(span.column) + span.text.chars().count() as u16;
+        }
+        queue!(self.output, Clear(ClearType::UntilNewLine))?;
+        Ok(())
+    }
+
+    fn draw_overlay(&mut self, column: u16, row: u16, text: &str) -> Result<(), Error> {
+        queue!(
+            self.output,
+            cursor::MoveTo(column, row),
+            SetBackgroundColor(BACKGROUND_COLOR),
+            Print(text),
+        )?;
+        Ok(())
+    }
+
+    // The terminal keeps what it shows when it is resized
+    fn resize(&mut self, _columns: u16, _rows: u16) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), Error> {
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+// Puts the terminal in raw mode, and restores it when dropped: when the game
+// quits, fails, or panics while unwinding. The commands that set the terminal
+// up further are the caller's, restoring undoes all of them.
+pub struct TerminalGuard(());
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self, io::Error> {
+        enable_raw_mode()?;
+        TERMINAL_IS_SET_UP.store(true, Ordering::SeqCst);
+        Ok(TerminalGuard(()))
+    }
+
+    // Restore the terminal, with the error dropping the guard would ignore
+    pub fn restore(self) -> Result<(), io::Error> {
+        restore_terminal()
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore_terminal();
+    }
+}
+
+// Panics restore the terminal before their message is printed, or it would be
+// lost on the alternate screen and the shell left in raw mode
+pub fn restore_terminal_on_panic() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+}
+
+// Only the first call restores the terminal, and only when it was set up
+fn restore_terminal() -> Result<(), io::Error> {
+    if !TERMINAL_IS_SET_UP.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        ResetColor,
+        cursor::Show,
+        LeaveAlternateScreen
+    )
+}
+
+// The color of the terminal palette for a color of the library
+const fn terminal_color(color: color::Color) -> Color {
+    match color {
+        color::Color::Black => Color::Black,
+        color::Color::DarkGrey => Color::DarkGrey,
+        color::Color::Red => Color::Red,
+        color::Color::DarkRed => Color::DarkRed,
+        color::Color::Green => Color::Green,
+        color::Color::DarkGreen => Color::DarkGreen,
+        color::Color::Yellow => Color::Yellow,
+        color::Color::DarkYellow => Color::DarkYellow,
+        color::Color::Blue => Color::Blue,
+        color::Color::DarkBlue => Color::DarkBlue,
+        color::Color::Magenta => Color::Magenta,
+        color::Color::DarkMagenta => Color::DarkMagenta,
+        color::Color::Cyan => Color::Cyan,
+        color::Color::DarkCyan => Color::DarkCyan,
+        color::Color::White => Color::White,
+        color::Color::Grey => Color::Grey,
+        color::Color::Rgb { r, g, b } => Color::Rgb { r, g, b },
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::SystemTime;
+
+/// A small pseudorandom number generator (SplitMix64). The same seed always
+/// gives the same numbers, which keeps runs reproducible.
+/// <https://prng.di.unimi.it/splitmix64.c>
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Numbers starting from a seed
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    /// A seed that differs from one run to the next
+    pub fn time_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default()
+    }
+
+    /// The next number, any u64 being as likely
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// True with the given probability, from 0 to 1
+    pub fn chance(&mut self, probability: f64) -> bool {
+        // The 53 high bits make a uniform float in [0, 1)
+        ((self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64) < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_give_the_same_numbers() {
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+        let numbers: Vec<u64> = (0..4).map(|_| first.next_u64()).collect();
+
+        assert_eq!(
+            numbers,
+            (0..4).map(|_| second.next_u64()).collect::<Vec<_>>()
+        );
+        assert_ne!(numbers[0], Random::new(43).next_u64());
+        assert!(!Random::new(1).chance(0.0));
+        assert!(Random::new(1).chance(1.0));
+    }
+}
@@ -0,0 +1,121 @@
+use super::Renderer;
+use super::ScreenCell;
+use super::Span;
+use super::Text;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::error::Error;
+
+/// Keeps the screen in memory with the colors it is drawn in, so tests can
+/// check what a frontend draws without a terminal. The text is drawn as by
+/// [`Text`], and the screen counts how often it was presented.
+pub struct Memory {
+    text: Text,
+    backgrounds: Vec<Vec<Color>>,
+    foregrounds: Vec<Vec<Option<Color>>>,
+    /// The number of times the screen was presented
+    pub presented: usize,
+}
+
+impl Memory {
+    /// An empty screen of a size
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Memory {
+            text: Text::new(columns, rows),
+            backgrounds: vec![vec![BACKGROUND_COLOR; columns as usize]; rows as usize],
+            foregrounds: vec![vec![None; columns as usize]; rows as usize],
+            presented: 0,
+        }
+    }
+
+    /// The screen as plain text
+    pub fn text(&self) -> String {
+        self.text.to_string()
+    }
+
+    /// A row of the screen as plain text, or an empty line outside of it
+    pub fn line(&self, row: u16) -> String {
+        self.text()
+            .lines()
+            .nth(row as usize)
+            .unwrap_or("")
+            .to_string()
+    }
+
+    /// The color a column of a row is painted in
+    pub fn background(&self, column: u16, row: u16) -> Option<Color> {
+        self.backgrounds
+            .get(row as usize)?
+            .get(column as usize)
+            .copied()
+    }
+
+    /// The color of the text at a column of a row, when it isn't the default
+    pub fn foreground(&self, column: u16, row: u16) -> Option<Color> {
+        *self.foregrounds.get(row as usize)?.get(column as usize)?
+    }
+
+    // Give columns of a row colors, cutting them at the right edge
+    fn paint(&mut self, column: u16, row: u16, colors: &[(Color, Option<Color>)]) {
+        let (Some(backgrounds), Some(foregrounds)) = (
+            self.backgrounds.get_mut(row as usize),
+            self.foregrounds.get_mut(row as usize),
+        ) else {
+            return;
+        };
+        let places = backgrounds.iter_mut().zip(foregrounds.iter_mut());
+        for ((background, foreground), colors) in places.skip(column as usize).zip(colors) {
+            (*background, *foreground) = *colors;
+        }
+    }
+}
+
+impl Renderer for Memory {
+    fn draw_cells(&mut self, cells: &[ScreenCell]) -> Result<(), Error> {
+        for cell in cells {
+            self.paint(
+                cell.column,
+                cell.row,
+                &vec![(cell.color, None); cell.width as usize],
+            );
+        }
+        self.text.draw_cells(cells)
+    }
+
+    fn draw_status(&mut self, row: u16, spans: &[Span]) -> Result<(), Error> {
+        let columns = self.text.columns() as usize;
+        self.paint(0, row, &vec![(BACKGROUND_COLOR, None); columns]);
+        for span in spans {
+            let length = span.text.chars().count();
+            self.paint(
+                span.column,
+                row,
+                &vec![(BACKGROUND_COLOR, span.color); length],
+            );
+        }
+        self.text.draw_status(row, spans)
+    }
+
+    fn draw_overlay(&mut self, column: u16, row: u16, text: &str) -> Result<(), Error> {
+        let length = text.chars().count();
+        self.paint(column, row, &vec![(BACKGROUND_COLOR, None); length]);
+        self.text.draw_overlay(column, row, text)
+    }
+
+    fn resize(&mut self, columns: u16, rows: u16) -> Result<(), Error> {
+        self.backgrounds.resize(rows as usize, Vec::new());
+        self.foregrounds.resize(rows as usize, Vec::new());
+        for line in &mut self.backgrounds {
+            line.resize(columns as usize, BACKGROUND_COLOR);
+        }
+        for line in &mut self.foregrounds {
+            line.resize(columns as usize, None);
+        }
+        self.text.resize(columns, rows)
+    }
+
+    fn present(&mut self) -> Result<(), Error> {
+        self.presented += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+mod memory;
+pub use memory::Memory;
+
+mod text;
+pub use text::Text;
+
+use crate::color::Color;
+use crate::error::Error;
+
+/// A cell of the grid on the screen: `width` columns of a row, from a column,
+/// painted in a color
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScreenCell {
+    /// The first column of the cell
+    pub column: u16,
+    /// The row of the cell
+    pub row: u16,
+    /// The number of columns of the cell
+    pub width: u16,
+    /// The color of the cell
+    pub color: Color,
+}
+
+/// Text of a status line, written from a column in a color, or in the default
+/// color of the text when there is none
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    /// The column the text starts at
+    pub column: u16,
+    /// The text
+    pub text: String,
+    /// The color of the text, if not the default one
+    pub color: Option<Color>,
+}
+
+impl Span {
+    /// Text in the default color
+    pub fn new(column: u16, text: impl Into<String>) -> Self {
+        Span {
+            column,
+            text: text.into(),
+            color: None,
+        }
+    }
+
+    /// Text in a color
+    pub fn colored(column: u16, text: impl Into<String>, color: Color) -> Self {
+        Span {
+            column,
+            text: text.into(),
+            color: Some(color),
+        }
+    }
+}
+
+/// Where a frontend draws its screen: a terminal, a string, or memory in tests.
+/// Screens are rows of character columns. The cells of the grid are painted in
+/// their colors, status lines and overlays are text on the background color.
+/// What is drawn may only be shown once it is presented.
+pub trait Renderer {
+    /// Paint cells of the grid
+    fn draw_cells(&mut self, cells: &[ScreenCell]) -> Result<(), Error>;
+
+    /// Replace a whole row with a status line
+    fn draw_status(&mut self, row: u16, spans: &[Span]) -> Result<(), Error>;
+
+    /// Write text over what is drawn on a row, from a column, such as a panel
+    /// over the cells
+    fn draw_overlay(&mut self, column: u16, row: u16, text: &str) -> Result<(), Error>;
+
+    /// The screen changed size, to this many columns and rows
+    fn resize(&mut self, columns: u16, rows: u16) -> Result<(), Error>;
+
+    /// Show what was drawn
+    fn present(&mut self) -> Result<(), Error>;
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::fmt::Display;
+
+use super::Renderer;
+use super::ScreenCell;
+use super::Span;
+use crate::color::BACKGROUND_COLOR;
+use crate::error::Error;
+
+// How cells of any color but the background are written
+const CELL_CHARACTER: char = '#';
+
+/// Draws the screen as plain text, with cells of any color but the background
+/// as `#`. The text of the screen is its `to_string`, one line per row without
+/// trailing spaces. What is drawn outside of the screen is cut.
+pub struct Text {
+    columns: u16,
+    rows: Vec<Vec<char>>,
+}
+
+impl Text {
+    /// An empty screen of a size
+    pub fn new(columns: u16, rows: u16) -> Self {
+        Text {
+            columns,
+            rows: vec![vec![' '; columns as usize]; rows as usize],
+        }
+    }
+
+    /// The number of columns of the screen
+    pub fn columns(&self) -> u16 {
+        self.columns
+    }
+
+    /// The number of rows of the screen
+    pub fn rows(&self) -> u16 {
+        self.rows.len() as u16
+    }
+
+    /// The character at a column of a row, if it is on the screen
+    pub fn get(&self, column: u16, row: u16) -> Option<char> {
+        self.rows.get(row as usize)?.get(column as usize).copied()
+    }
+
+    // Write characters from a column of a row, cutting them at the right edge
+    fn write(&mut self, column: u16, row: u16, characters: impl Iterator<Item = char>) {
+        let Some(line) = self.rows.get_mut(row as usize) else {
+            return;
+        };
+        for (place, character) in line.iter_mut().skip(column as usize).zip(characters) {
+            *place = character;
+        }
+    }
+}
+
+impl Renderer for Text {
+    fn draw_cells(&mut self, cells: &[ScreenCell]) -> Result<(), Error> {
+        for cell in cells {
+            let character = if cell.color == BACKGROUND_COLOR {
+                ' '
+            } else {
+                CELL_CHARACTER
+            };
+            let characters = std::iter::repeat_n(character, cell.width as usize);
+            self.write(cell.column, cell.row, characters);
+        }
+        Ok(())
+    }
+
+    fn draw_status(&mut self, row: u16, spans: &[Span]) -> Result<(), Error> {
+        self.write(0, row, std::iter::repeat_n(' ', self.columns as usize));
+        for span in spans {
+            self.write(span.column, row, span.text.chars());
+        }
+        Ok(())
+    }
+
+    fn draw_overlay(&mut self, column: u16, row: u16, text: &str) -> Result<(), Error> {
+        self.write(column, row, text.chars());
+        Ok(())
+    }
+
+    // What was drawn stays in the top left corner
+    fn resize(&mut self, columns: u16, rows: u16) -> Result<(), Error> {
+        self.columns = columns;
+        self.rows.resize(rows as usize, Vec::new());
+        for line in &mut self.rows {
+            line.resize(columns as usize, ' ');
+        }
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl Display for Text {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .rows
+            .iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        formatter.write_str(&lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::CELL_COLOR;
+
+    #[test]
+    fn screens_are_drawn_as_text() {
+        let mut text = Text::new(8, 3);
+        text.draw_status(0, &[Span::new(0, "Gen: 1"), Span::new(7, "cut")])
+            .unwrap();
+        let cells =
+            [(1, CELL_COLOR), (3, BACKGROUND_COLOR), (5, CELL_COLOR)].map(|(column, color)| {
+                ScreenCell {
+                    column,
+                    row: 1,
+                    width: 2,
+                    color,
+                }
+            });
+        text.draw_cells(&cells).unwrap();
+        text.draw_overlay(6, 2, "|x").unwrap();
+
+        assert_eq!(text.to_string(), "Gen: 1 c\n ##  ##\n      |x");
+
+        // Status lines replace their whole row
+        text.draw_status(0, &[Span::new(2, "p")]).unwrap();
+        assert_eq!(text.to_string().lines().next(), Some("  p"));
+    }
+}
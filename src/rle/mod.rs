@@ -0,0 +1,309 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::automaton;
+use crate::automaton::Automaton;
+use crate::error::Error;
+use crate::export::Region;
+use crate::grid::Grid;
+use crate::grid::DEAD;
+
+// Lines of written patterns are kept this short, as other programs expect
+const LINE_LENGTH: usize = 70;
+
+/// A pattern in the run length encoded format of most pattern collections.
+///
+/// #N Glider
+/// x = 3, y = 3, rule = B3/S23
+/// bo$2bo$3o!
+///
+/// After the comments, the header gives the size of the pattern and its rule.
+/// The rows follow, as runs of cells in a state: a count, left out when it is
+/// 1, then b for dead cells and o for live ones, or '.' and A to X for the
+/// states of automata with more than two. $ ends a row and ! the pattern.
+/// <https://conwaylife.com/wiki/Run_Length_Encoded>
+pub struct Rle {
+    /// The rule of the header
+    pub rule: Option<Box<dyn Automaton>>,
+    columns: u32,
+    rows: u32,
+    // The (column, row) and the state of each cell that isn't dead
+    cells: Vec<((u32, u32), u8)>,
+}
+
+impl Rle {
+    /// Replace the cells of the grid with the pattern, in its middle. Whatever
+    /// doesn't fit in the grid is left out, and so are the states the
+    /// automaton of the grid doesn't have.
+    pub fn place(&self, grid: &mut Grid) {
+        grid.clear();
+        let column = (grid.width as i64 - self.columns as i64) / 2;
+        let row = (grid.height as i64 - self.rows as i64) / 2;
+        let states = grid.automaton().states();
+        for ((cell_column, cell_row), state) in &self.cells {
+            let width = column + *cell_column as i64;
+            let height = row + *cell_row as i64;
+            if (0..grid.width as i64).contains(&width)
+                && (0..grid.height as i64).contains(&height)
+                && *state < states
+            {
+                grid.set_cell((width as u16, height as u16), *state);
+            }
+        }
+    }
+}
+
+impl FromStr for Rle {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |message: String| Error::InvalidPattern(format!("invalid RLE file: {message}"));
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let header = lines
+            .next()
+            .ok_or_else(|| invalid("the file is empty".to_string()))?;
+        let mut rle = Rle {
+            rule: None,
+            columns: 0,
+            rows: 0,
+            cells: Vec::new(),
+        };
+        // Rules can have commas of their own, so the rule is the rest of the line
+        let (sizes, rule) = match header.split_once("rule") {
+            Some((sizes, rule)) => (sizes, rule.trim().strip_prefix('=')),
+            None => (header, None),
+        };
+        if let Some(rule) = rule {
+            rle.rule = Some(automaton::parse(rule.trim())?);
+        }
+        let (mut has_columns, mut has_rows) = (false, false);
+        for field in sizes.split(',').filter(|field| !field.trim().is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("invalid header \"{header}\"")))?;
+            let number = || {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid size \"{}\"", value.trim())))
+            };
+            match key.trim() {
+                "x" => (rle.columns, has_columns) = (number()?, true),
+                "y" => (rle.rows, has_rows) = (number()?, true),
+                _ => {}
+            }
+        }
+        if !has_columns || !has_rows {
+            return Err(invalid("the header has no x and y".to_string()));
+        }
+
+        let (mut column, mut row): (u32, u32) = (0, 0);
+        let mut count: Option<u32> = None;
+        let mut prefix: Option<char> = None;
+        'lines: for line in lines {
+            for character in line.chars() {
+                if let Some(digit) = character.to_digit(10) {
+                    count = Some(
+                        count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(digit))
+                            .ok_or_else(|| invalid("a run is too long".to_string()))?,
+                    );
+                    continue;
+                }
+                let run = count.take().unwrap_or(1);
+                let too_long = || invalid("a run is too long".to_string());
+                let state = match (prefix.take(), character) {
+                    (_, '!') => break 'lines,
+                    (None, '$') => {
+                        (column, row) = (0, row.checked_add(run).ok_or_else(too_long)?);
+                        continue;
+                    }
+                    (None, 'b' | '.') => DEAD,
+                    (None, 'o') => 1,
+                    (None, 'A'..='X') => character as u8 - b'A' + 1,
+                    // States above 24 are written after a prefix, p for 25 to 48
+                    (None, 'p'..='y') => {
+                        (count, prefix) = (Some(run), Some(character));
+                        continue;
+                    }
+                    (Some(prefix), 'A'..='X') => {
+                        let state = (prefix as u32 - 'p' as u32 + 1) * 24
+                            + (character as u32 - 'A' as u32)
+                            + 1;
+                        u8::try_from(state)
+                            .map_err(|_| invalid(format!("invalid state {state}")))?
+                    }
+                    _ if character.is_whitespace() => continue,
+                    _ => return Err(invalid(format!("unexpected '{character}'"))),
+                };
+                let end = column.checked_add(run).ok_or_else(too_long)?;
+                // Live cells stay in the size of the header, so a run can't
+                // make more of them than the pattern has room for
+                if state != DEAD {
+                    if end > rle.columns || row >= rle.rows {
+                        return Err(invalid(format!(
+                            "cells outside the {} by {} of the header",
+                            rle.columns, rle.rows
+                        )));
+                    }
+                    rle.cells
+                        .extend((column..end).map(|column| ((column, row), state)));
+                }
+                column = end;
+            }
+        }
+        Ok(rle)
+    }
+}
+
+/// Read a pattern from an RLE file
+pub fn load(path: &Path) -> Result<Rle, Error> {
+    fs::read_to_string(path)?.parse()
+}
+
+/// Write a region of the grid in the RLE format
+pub fn save(path: &Path, grid: &Grid, region: &Region) -> Result<(), Error> {
+    Ok(fs::write(path, write(grid, region)?)?)
+}
+
+/// A region of the grid in the RLE format, with the rule of the grid
+pub fn write(grid: &Grid, region: &Region) -> Result<String, Error> {
+    let states = grid.automaton().states();
+    if states > 25 {
+        return Err(Error::Export(
+            "RLE files are only written for automata of up to 25 states".to_string(),
+        ));
+    }
+    let state_character = |state: u8| match (states, state) {
+        (2, DEAD) => 'b',
+        (2, _) => 'o',
+        (_, DEAD) => '.',
+        (_, state) => (b'A' + state - 1) as char,
+    };
+
+    // The runs of each row, without the dead cells that end it, and the rows
+    // that end before each row that isn't empty
+    let mut runs: Vec<(u32, char)> = Vec::new();
+    let mut last_row = 0;
+    for row in 0..region.rows {
+        let mut row_runs: Vec<(u32, char)> = Vec::new();
+        for column in 0..region.columns {
+            let state = grid
+                .get(region.left + column, region.top + row)
+                .copied()
+                .unwrap_or(DEAD);
+            match row_runs.last_mut() {
+                Some((count, character)) if *character == state_character(state) => *count += 1,
+                _ => row_runs.push((1, state_character(state))),
+            }
+        }
+        if row_runs
+            .last()
+            .is_some_and(|(_, character)| *character == state_character(DEAD))
+        {
+            row_runs.pop();
+        }
+        if row_runs.is_empty() {
+            continue;
+        }
+        if row > last_row {
+            runs.push(((row - last_row) as u32, '$'));
+        }
+        runs.extend(row_runs);
+        last_row = row;
+    }
+    runs.push((1, '!'));
+
+    let mut text = format!(
+        "#C Written by game-of-life\nx = {}, y = {}, rule = {}\n",
+        region.columns,
+        region.rows,
+        grid.automaton().name()
+    );
+    let mut line = String::new();
+    for (count, character) in runs {
+        let run = match count {
+            1 => character.to_string(),
+            _ => format!("{count}{character}"),
+        };
+        if line.len() + run.len() > LINE_LENGTH {
+            text.push_str(&line);
+            text.push('\n');
+            line.clear();
+        }
+        line.push_str(&run);
+    }
+    text.push_str(&line);
+    text.push('\n');
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(grid: &Grid) -> Vec<u8> {
+        (0..grid.height)
+            .flat_map(|height| (0..grid.width).map(move |width| grid[(width, height)]))
+            .collect()
+    }
+
+    #[test]
+    fn pattern_files_are_read() {
+        let rle: Rle = "#N Glider\n#C A comment\nx = 3, y = 3, rule = B36/S23\nbo$2bo$\n3o!\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rle.rule.as_ref().map(|rule| rule.name()),
+            Some("B36/S23".to_string())
+        );
+
+        let mut grid = Grid::new(7, 7);
+        grid.cycle_cell((0, 0));
+        rle.place(&mut grid);
+        assert_eq!(grid.population, 5);
+        assert_eq!(grid.bounding_box(), Some((2, 2, 4, 4)));
+        assert_eq!(grid[(3, 2)], 1);
+
+        for text in ["", "bo$2bo!", "x = 3\nbo!", "x = 3, y = 3\nbo$2bz!"] {
+            assert!(text.parse::<Rle>().is_err(), "{text:?} should be invalid");
+        }
+    }
+
+    #[test]
+    fn runs_stay_in_the_header() {
+        for text in [
+            "x = 3, y = 1\n4294967295b2o!",
+            "x = 3, y = 1\n100000000o!",
+            "x = 3, y = 2\n4294967295$o!",
+            "x = 3, y = 2\n2$o!",
+            "x = 3, y = 1\n99999999999o!",
+        ] {
+            assert!(text.parse::<Rle>().is_err(), "{text:?} should be invalid");
+        }
+    }
+
+    #[test]
+    fn written_patterns_read_back() {
+        let mut grid = Grid::new(90, 6);
+        grid.set_automaton(automaton::parse("345/2/4").unwrap());
+        for (index, state) in [((1, 1), 1), ((2, 1), 2), ((3, 1), 3), ((85, 4), 1)] {
+            grid.set_cell(index, state);
+        }
+
+        let text = write(&grid, &Region::whole(&grid)).unwrap();
+        assert!(text.contains("x = 90, y = 6, rule = 345/2/4\n$.ABC3$85.A!"));
+        let mut read_grid = Grid::new(90, 6);
+        read_grid.set_automaton(automaton::parse("345/2/4").unwrap());
+        text.parse::<Rle>().unwrap().place(&mut read_grid);
+        assert_eq!(cells(&read_grid), cells(&grid));
+    }
+}
@@ -0,0 +1,122 @@
+// Hensel notation names the ways live neighbors can be arranged around a cell,
+// up to rotation and reflection, with a digit for how many there are and a
+// letter for their shape (2a: two adjacent neighbors, 2i: two opposite edges...).
+// https://conwaylife.com/wiki/Isotropic_non-totalistic_rule
+//
+// A neighborhood has bit i set when neighbor i is alive, with the neighbors
+// numbered clockwise from the north: N, NE, E, SE, S, SW, W, NW.
+
+const N: u8 = 1 << 0;
+const NE: u8 = 1 << 1;
+const E: u8 = 1 << 2;
+const SE: u8 = 1 << 3;
+const S: u8 = 1 << 4;
+const SW: u8 = 1 << 5;
+const W: u8 = 1 << 6;
+const NW: u8 = 1 << 7;
+
+// One neighborhood of each letter, for 1 to 4 live neighbors, in the canonical
+// letter order of Hensel and Golly: c, e, k, a, i, n, y, q, j, r, t, w, z.
+// Neighborhoods of 5 to 7 are the complements of those of 3 to 1.
+const SHAPES: [&[(char, u8)]; 5] = [
+    &[],
+    &[('c', NE), ('e', N)],
+    &[
+        ('c', NE | SE),
+        ('e', N | E),
+        ('k', N | SE),
+        ('a', N | NE),
+        ('i', N | S),
+        ('n', NE | SW),
+    ],
+    &[
+        ('c', NE | SE | SW),
+        ('e', N | E | S),
+        ('k', N | E | SW),
+        ('a', N | NE | E),
+        ('i', N | NE | NW),
+        ('n', N | NE | SE),
+        ('y', N | SE | SW),
+        ('q', N | NE | SW),
+        ('j', N | NE | W),
+        ('r', N | NE | S),
+    ],
+    &[
+        ('c', NE | SE | SW | NW),
+        ('e', N | E | S | W),
+        ('k', N | NE | SE | W),
+        ('a', N | NE | E | SE),
+        ('i', N | NE | SE | S),
+        ('n', N | NE | SE | NW),
+        ('y', N | NE | SE | SW),
+        ('q', N | NE | E | SW),
+        ('j', N | NE | S | W),
+        ('r', N | NE | E | S),
+        ('t', N | NE | S | NW),
+        ('w', N | NE | SW | W),
+        ('z', N | NE | S | SW),
+    ],
+];
+
+/// The letters of a number of live neighbors, in canonical order
+pub fn letters(count: u32) -> impl Iterator<Item = char> {
+    let shapes = match count {
+        0..=4 => SHAPES[count as usize],
+        5..=8 => SHAPES[8 - count as usize],
+        _ => &[],
+    };
+    shapes.iter().map(|(letter, _)| *letter)
+}
+
+/// The letter of a neighborhood, None when it is the only one of its count
+pub fn letter(neighborhood: u8) -> Option<char> {
+    let count = neighborhood.count_ones();
+    if count > 4 {
+        return letter(!neighborhood);
+    }
+    SHAPES[count as usize]
+        .iter()
+        .find(|(_, shape)| symmetries(*shape).any(|symmetry| symmetry == neighborhood))
+        .map(|(letter, _)| *letter)
+}
+
+// The neighborhood turned by quarter turns, and mirrored
+fn symmetries(neighborhood: u8) -> impl Iterator<Item = u8> {
+    // Mirroring swaps neighbor i with neighbor 8 - i, keeping N and S in place
+    let mirrored = neighborhood.reverse_bits().rotate_left(1);
+    (0..4).flat_map(move |turns| {
+        [
+            neighborhood.rotate_left(2 * turns),
+            mirrored.rotate_left(2 * turns),
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_neighborhood_has_one_letter() {
+        for count in 1..=7 {
+            let letters: Vec<char> = letters(count).collect();
+            let mut neighborhoods_by_letter = vec![0; letters.len()];
+            for neighborhood in (0..=255_u8).filter(|bits| bits.count_ones() == count) {
+                let letter = letter(neighborhood).expect("the neighborhood has no letter");
+                let position = letters.iter().position(|known| *known == letter).unwrap();
+                neighborhoods_by_letter[position] += 1;
+            }
+            // Every letter names at least one neighborhood, so no two share one
+            assert!(neighborhoods_by_letter.iter().all(|count| *count > 0));
+        }
+        assert_eq!(letter(0), None);
+        assert_eq!(letter(255), None);
+    }
+
+    #[test]
+    fn complements_share_letters() {
+        assert_eq!(letter(!(N | NE)), Some('a'));
+        assert_eq!(letter(!(NE | SE | SW)), Some('c'));
+        assert_eq!(letter(W | NW | N | NE), Some('a'));
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt::Write as _;
+
+use super::Neighborhood;
+use crate::grid::SummedAreaTable;
+
+pub const MAX_RADIUS: u8 = 10;
+
+// The most cells a neighborhood can have, with the cell itself
+const MAX_SIZE: usize = (2 * MAX_RADIUS as usize + 1) * (2 * MAX_RADIUS as usize + 1);
+
+/// A set of neighbor counts from 0 to MAX_SIZE
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Counts([u64; MAX_SIZE.div_ceil(64)]);
+
+impl Counts {
+    pub fn contains(&self, count: usize) -> bool {
+        count <= MAX_SIZE && self.0[count / 64] & 1 << (count % 64) != 0
+    }
+
+    fn insert(&mut self, count: usize) {
+        self.0[count / 64] |= 1 << (count % 64);
+    }
+
+    // The counts as (first, last) ranges, in increasing order
+    fn ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for count in (0..=MAX_SIZE).filter(|count| self.contains(*count)) {
+            match ranges.last_mut() {
+                Some((_, last)) if *last + 1 == count => *last = count,
+                _ => ranges.push((count, count)),
+            }
+        }
+        ranges
+    }
+}
+
+/// A Larger than Life rule counts the live cells within `radius` of a cell,
+/// instead of only the eight next to it, and gives the counts of births and
+/// survivals as ranges.
+/// <https://conwaylife.com/wiki/Larger_than_Life>
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LargerThanLife {
+    /// How far from the cell its neighbors are counted
+    pub radius: u8,
+    /// Whether the cell itself is counted
+    pub includes_center: bool,
+    /// Moore for the square around the cell, von Neumann for the diamond
+    pub neighborhood: Neighborhood,
+    /// The counts at which dead cells are born
+    pub birth: Counts,
+    /// The counts at which live cells survive
+    pub survival: Counts,
+}
+
+impl LargerThanLife {
+    /// The number of cells counted
+    pub fn size(&self) -> usize {
+        let radius = self.radius as usize;
+        let around = match self.neighborhood {
+            Neighborhood::VonNeumann => 2 * radius * (radius + 1),
+            _ => (2 * radius + 1) * (2 * radius + 1) - 1,
+        };
+        around + self.includes_center as usize
+    }
+
+    /// The live cells within range of a cell. The square of the Moore
+    /// neighborhood is a single rectangle, the diamond of the von Neumann
+    /// neighborhood is counted one row at a time.
+    pub fn count(&self, (width, height): (u16, u16), table: &SummedAreaTable) -> u16 {
+        let (column, row) = (width as isize, height as isize);
+        let radius = self.radius as isize;
+        let count = match self.neighborhood {
+            Neighborhood::VonNeumann => (-radius..=radius)
+                .map(|row_offset| {
+                    let reach = radius - row_offset.abs();
+                    let row = row + row_offset;
+                    table.sum(column - reach, row, column + reach, row)
+                })
+                .sum(),
+            _ => table.sum(column - radius, row - radius, column + radius, row + radius),
+        };
+        // The table also counts the cell itself
+        let center_is_counted = self.includes_center || table.sum(column, row, column, row) == 0;
+        (count - !center_is_counted as u32) as u16
+    }
+
+    /// Rules whose counts are single ranges are written as Golly writes Larger
+    /// than Life rules (R5,C0,M1,S34..58,B34..45,NM), others in the HROT
+    /// notation that allows lists of ranges (R2,C0,M0,S2-3,5,B3,NM).
+    pub fn rule_string(&self, states: u8) -> String {
+        let (survival, birth) = (self.survival.ranges(), self.birth.ranges());
+        let is_golly = survival.len() == 1 && birth.len() == 1;
+        let list = |ranges: Vec<(usize, usize)>| -> String {
+            let mut list = String::new();
+            for (index, (first, last)) in ranges.into_iter().enumerate() {
+                if index > 0 {
+                    list.push(',');
+                }
+                // Writing into a String never fails
+                let _ = match (is_golly, first == last) {
+                    (true, _) => write!(list, "{first}..{last}"),
+                    (false, true) => write!(list, "{first}"),
+                    (false, false) => write!(list, "{first}-{last}"),
+                };
+            }
+            list
+        };
+
+        format!(
+            "R{},C{},M{},S{},B{},N{}",
+            self.radius,
+            if states == 2 { 0 } else { states },
+            self.includes_center as u8,
+            list(survival),
+            list(birth),
+            if self.neighborhood == Neighborhood::VonNeumann {
+                'N'
+            } else {
+                'M'
+            }
+        )
+    }
+}
+
+/// Whether a rule string is a Larger than Life rule, which starts with the radius
+pub fn is_rule_string(text: &str) -> bool {
+    let mut characters = text.chars();
+    matches!(characters.next(), Some('R' | 'r'))
+        && characters
+            .next()
+            .is_some_and(|digit| digit.is_ascii_digit())
+}
+
+/// Parse a rule in Golly's or in HROT notation into the rule and its number of states
+pub fn parse(text: &str) -> Result<(LargerThanLife, u8), String> {
+    let mut rule = LargerThanLife {
+        radius: 0,
+        includes_center: false,
+        neighborhood: Neighborhood::Moore,
+        birth: Counts::default(),
+        survival: Counts::default(),
+    };
+    let mut states = 2;
+
+    // Ranges are added to the counts of the last S or B
+    let mut list: Option<char> = None;
+    for part in text.split(',').map(str::trim) {
+        let Some(prefix) = part.chars().next() else {
+            continue;
+        };
+        let value = &part[prefix.len_utf8()..];
+        let number = |name: &str, max: usize| -> Result<usize, String> {
+            match value.parse() {
+                Ok(number) if number <= max => Ok(number),
+                _ => Err(format!("{name} is a number from 0 to {max}")),
+            }
+        };
+
+        match prefix.to_ascii_uppercase() {
+            'R' => {
+                rule.radius = number("the radius", MAX_RADIUS as usize)? as u8;
+                if rule.radius == 0 {
+                    return Err(format!("the radius is from 1 to {MAX_RADIUS}"));
+                }
+            }
+            'C' => states = number("the number of states", u8::MAX as usize)?.max(2) as u8,
+            'M' => rule.includes_center = number("the middle", 1)? == 1,
+            'N' => {
+                rule.neighborhood = match value {
+                    "M" | "m" => Neighborhood::Moore,
+                    "N" | "n" => Neighborhood::VonNeumann,
+                    _ => return Err("the neighborhood is M or N".to_string()),
+                }
+            }
+            'S' => {
+                list = Some('S');
+                add_range(&mut rule.survival, value)?;
+            }
+            'B' => {
+                list = Some('B');
+                add_range(&mut rule.birth, value)?;
+            }
+            digit if digit.is_ascii_digit() => match list {
+                Some('S') => add_range(&mut rule.survival, part)?,
+                Some(_) => add_range(&mut rule.birth, part)?,
+                None => return Err(format!("\"{part}\" doesn't follow S or B")),
+            },
+            _ => return Err(format!("unknown part \"{part}\"")),
+        }
+    }
+
+    if rule.radius == 0 {
+        return Err("the radius is missing".to_string());
+    }
+    let size = rule.size();
+    if (size + 1..=MAX_SIZE)
+        .any(|count| rule.birth.contains(count) || rule.survival.contains(count))
+    {
+        return Err(format!("counts are at most {size} in this neighborhood"));
+    }
+    Ok((rule, states))
+}
+
+// Add a range written as 3..5, 3-5 or 3, an empty list has none
+fn add_range(counts: &mut Counts, range: &str) -> Result<(), String> {
+    if range.is_empty() {
+        return Ok(());
+    }
+    let (first, last) = range
+        .split_once("..")
+        .or_else(|| range.split_once('-'))
+        .unwrap_or((range, range));
+    match (first.parse::<usize>(), last.parse::<usize>()) {
+        (Ok(first), Ok(last)) if first <= last && last <= MAX_SIZE => {
+            (first..=last).for_each(|count| counts.insert(count));
+            Ok(())
+        }
+        _ => Err(format!("invalid range \"{range}\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notations_round_trip() {
+        for text in [
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R10,C3,M0,S1..100,B50..60,NN",
+            "R2,C0,M0,S2-3,5,B3,NM",
+            "R1,C0,M0,S,B3,NM",
+        ] {
+            let (rule, states) = parse(text).unwrap();
+            assert_eq!(rule.rule_string(states), text);
+        }
+        let (rule, states) = parse("R1,C2,S2-3,B3").unwrap();
+        assert_eq!(rule.rule_string(states), "R1,C0,M0,S2..3,B3..3,NM");
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        for text in [
+            "R0,S1,B1",
+            "R11,S1,B1",
+            "R1,S9,B1",
+            "R1,S1,B1,NX",
+            "S1,B1",
+            "R1,4",
+        ] {
+            assert!(parse(text).is_err(), "{text} should be invalid");
+        }
+        assert!(parse("R1,M1,S9,B1").is_ok());
+    }
+}
@@ -0,0 +1,509 @@
+use std::fmt;
+use std::fmt::Display;
+use std::str::FromStr;
+
+mod hensel;
+use hensel::letter;
+use hensel::letters;
+
+mod larger_than_life;
+pub use larger_than_life::LargerThanLife;
+
+mod neighborhood;
+pub use neighborhood::Neighborhood;
+
+use crate::automaton::Automaton;
+use crate::color::Color;
+use crate::color::BACKGROUND_COLOR;
+use crate::color::CELL_COLOR;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::grid::SummedAreaTable;
+use crate::grid::ALIVE;
+use crate::grid::DEAD;
+
+// Rules that can be given by name instead of a rule string
+const NAMED_RULES: [(&str, &str); 7] = [
+    ("life", "B3/S23"),
+    ("highlife", "B36/S23"),
+    ("seeds", "B2/S"),
+    ("starwars", "345/2/4"),
+    ("tlife", "B3/S2-i34q"),
+    ("bosco", "R5,C0,M1,S34..58,B34..45,NM"),
+    ("majority", "R4,C0,M1,S41..81,B41..81,NM"),
+];
+
+/// Whether a cell is born or survives depends on which of its eight neighbors
+/// are alive. Outer totalistic rules only look at how many are alive, isotropic
+/// non-totalistic rules also at how they are arranged, up to rotation and
+/// reflection (see `hensel`).
+///
+/// Outer totalistic rules can also count the live cells of other neighborhoods,
+/// which Golly writes as a suffix: B2/S34H for the hexagonal neighborhood, or
+/// of a larger range with Larger than Life rules (see `larger_than_life`).
+///
+/// Generations rules have more than two states. A live cell that doesn't survive
+/// doesn't die at once but decays through the states 2, 3, ... until it reaches
+/// the last state and dies. Decaying cells don't count as live neighbors and
+/// block births, since only dead cells can be born.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rule {
+    // Whether a dead cell is born (first half) or a live cell survives (second
+    // half), indexed by the neighborhood of the cell as described in `hensel`,
+    // or by the number of live neighbors for neighborhoods other than Moore's
+    transitions: [bool; 2 * NEIGHBORHOODS],
+    states: u8,
+    neighborhood: Neighborhood,
+    larger_than_life: Option<LargerThanLife>,
+}
+
+// The number of ways the eight neighbors of a cell can be alive or dead
+const NEIGHBORHOODS: usize = 256;
+// Neighborhoods of more cells write their counts as numbers separated by
+// commas, since they can be above 9: B3,12/S2,3N@ffffff
+const MAX_DIGIT_COUNT: u32 = 9;
+const BIRTH: usize = 0;
+const SURVIVAL: usize = NEIGHBORHOODS;
+
+impl Default for Rule {
+    // Conway's Game of Life
+    fn default() -> Self {
+        let mut transitions = [false; 2 * NEIGHBORHOODS];
+        for neighborhood in 0..NEIGHBORHOODS {
+            let count = neighborhood.count_ones();
+            transitions[BIRTH + neighborhood] = count == 3;
+            transitions[SURVIVAL + neighborhood] = count == 2 || count == 3;
+        }
+        Rule {
+            transitions,
+            states: 2,
+            neighborhood: Neighborhood::Moore,
+            larger_than_life: None,
+        }
+    }
+}
+
+impl Rule {
+    /// The neighborhood is the arrangement of live neighbors for the Moore
+    /// neighborhood, and their number for the others
+    pub fn next_state(&self, state: u8, neighborhood: u16) -> u8 {
+        let neighborhood = neighborhood as usize;
+        let (is_born, survives) = match &self.larger_than_life {
+            Some(rule) => (
+                rule.birth.contains(neighborhood),
+                rule.survival.contains(neighborhood),
+            ),
+            None => (
+                self.transitions[BIRTH + neighborhood],
+                self.transitions[SURVIVAL + neighborhood],
+            ),
+        };
+        match state {
+            DEAD if is_born => ALIVE,
+            DEAD => DEAD,
+            ALIVE if survives => ALIVE,
+            // Decay, or die when there are no states left to decay through
+            _ if state + 1 < self.states => state + 1,
+            _ => DEAD,
+        }
+    }
+
+    // The conditions of births or survivals, as neighbor counts followed by the
+    // letters of the arrangements they apply to. A count applying to all of its
+    // arrangements has no letters, and one applying to more than half of them
+    // lists those it doesn't apply to after a minus sign.
+    fn conditions(&self, offset: usize) -> String {
+        let transitions = &self.transitions[offset..offset + NEIGHBORHOODS];
+        let mut conditions = String::new();
+        if self.neighborhood != Neighborhood::Moore {
+            let counts = 0..=self.neighborhood.size();
+            let applying = counts.filter(|count| transitions[*count as usize]);
+            let separator = if self.neighborhood.size() > MAX_DIGIT_COUNT {
+                ","
+            } else {
+                ""
+            };
+            return applying
+                .map(|count| count.to_string())
+                .collect::<Vec<_>>()
+                .join(separator);
+        }
+
+        for count in 0..=8 {
+            let (applying, not_applying): (Vec<u8>, Vec<u8>) = (0..=255_u8)
+                .filter(|neighborhood| neighborhood.count_ones() == count)
+                .partition(|neighborhood| transitions[*neighborhood as usize]);
+            if applying.is_empty() {
+                continue;
+            }
+
+            conditions.push(char::from(b'0' + count as u8));
+            if not_applying.is_empty() {
+                continue;
+            }
+            let applying_letters: Vec<char> = applying.into_iter().filter_map(letter).collect();
+            let (listed, unlisted): (Vec<char>, Vec<char>) =
+                letters(count).partition(|letter| applying_letters.contains(letter));
+            if listed.len() > unlisted.len() {
+                conditions.push('-');
+                conditions.extend(unlisted);
+            } else {
+                conditions.extend(listed);
+            }
+        }
+        conditions
+    }
+}
+
+impl Automaton for Rule {
+    fn name(&self) -> String {
+        self.to_string()
+    }
+
+    fn states(&self) -> u8 {
+        self.states
+    }
+
+    fn step(&mut self, grid: &Grid) -> Vec<((u16, u16), u8)> {
+        let mut changed_cells = Vec::new();
+
+        // Other neighborhoods than Moore's only count their live cells. Their
+        // offsets can depend on whether the row is even or odd.
+        let neighborhood_offsets = match self.neighborhood {
+            Neighborhood::Moore => None,
+            neighborhood => Some([neighborhood.offsets(0), neighborhood.offsets(1)]),
+        };
+
+        // Larger than Life rules count the live cells of whole rectangles
+        let range = self
+            .larger_than_life
+            .map(|rule| (rule, SummedAreaTable::new(grid)));
+
+        for width in 0..grid.width {
+            for height in 0..grid.height {
+                let index = (width, height);
+                let neighborhood = match (&range, &neighborhood_offsets) {
+                    (Some((rule, table)), _) => rule.count(index, table),
+                    (None, None) => grid.neighborhood(index) as u16,
+                    (None, Some(offsets)) => {
+                        let offsets = &offsets[height as usize % 2];
+                        grid.count_alive_neighbors(index, offsets) as u16
+                    }
+                };
+                let state = grid[index];
+                let next_state = self.next_state(state, neighborhood);
+                if next_state != state {
+                    changed_cells.push((index, next_state));
+                }
+            }
+        }
+
+        changed_cells
+    }
+
+    // Decaying cells fade from orange to dark red
+    fn color(&self, _index: (u16, u16), state: u8) -> Color {
+        match state {
+            DEAD => BACKGROUND_COLOR,
+            ALIVE => CELL_COLOR,
+            _ => {
+                let last_decay_state = self.states.saturating_sub(3).max(1) as u16;
+                let fade = (state - 2) as u16 * 255 / last_decay_state;
+                Color::Rgb {
+                    r: (255 - fade * 159 / 255) as u8,
+                    g: (128 - fade * 128 / 255) as u8,
+                    b: 0,
+                }
+            }
+        }
+    }
+
+    // Clicking brings a cell to life, or clears it if it is in any other state
+    fn clicked_state(&self, state: u8) -> u8 {
+        if state == DEAD {
+            ALIVE
+        } else {
+            DEAD
+        }
+    }
+
+    fn is_staggered(&self) -> bool {
+        self.neighborhood.is_staggered()
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(rule) = &self.larger_than_life {
+            return write!(formatter, "{}", rule.rule_string(self.states));
+        }
+
+        let birth = self.conditions(BIRTH);
+        let survival = self.conditions(SURVIVAL);
+
+        // Life-like rules in B/S notation, Generations rules in S/B/C notation
+        if self.states == 2 {
+            write!(formatter, "B{birth}/S{survival}")?;
+        } else {
+            write!(formatter, "{survival}/{birth}/{}", self.states)?;
+        }
+        write!(formatter, "{}", self.neighborhood)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    // Accepts B/S notation (B3/S23), S/B notation (23/3), S/B/C notation for
+    // Generations rules (345/2/4), B/S/C notation (B2/S/C3) and rule names,
+    // followed by V, H or N@ and a custom mask for other neighborhoods, and
+    // Larger than Life rules (R5,C0,M1,S34..58,B34..45,NM).
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| Error::InvalidRule(format!("invalid rule \"{text}\": {reason}"));
+
+        let name: String = text
+            .chars()
+            .filter(|character| character.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        if let Some((_, rule)) = NAMED_RULES.iter().find(|(rule_name, _)| *rule_name == name) {
+            return rule.parse();
+        }
+
+        let text = text.trim();
+        if larger_than_life::is_rule_string(text) {
+            let (rule, states) =
+                larger_than_life::parse(text).map_err(|reason| invalid(&reason))?;
+            return Ok(Rule {
+                transitions: [false; 2 * NEIGHBORHOODS],
+                states,
+                neighborhood: Neighborhood::Moore,
+                larger_than_life: Some(rule),
+            });
+        }
+
+        let (text, neighborhood) = match text.to_ascii_uppercase().find("N@") {
+            Some(index) => (
+                &text[..index],
+                Neighborhood::parse_custom(&text[index + 2..])
+                    .ok_or_else(|| invalid("custom masks are 2, 6 or 12 hexadecimal digits"))?,
+            ),
+            None => match text.strip_suffix(['V', 'v']) {
+                Some(text) => (text, Neighborhood::VonNeumann),
+                None => match text.strip_suffix(['H', 'h']) {
+                    Some(text) => (text, Neighborhood::Hexagonal),
+                    None => (text, Neighborhood::Moore),
+                },
+            },
+        };
+
+        // Neighbor counts, each optionally followed by the letters of the
+        // arrangements it applies to, or by a minus and those it doesn't
+        let conditions = |text: &str, transitions: &mut [bool]| -> Result<(), Error> {
+            if neighborhood.size() > MAX_DIGIT_COUNT {
+                for count in text.split(',').filter(|_| !text.is_empty()) {
+                    match count.parse::<u32>() {
+                        Ok(count) if count <= neighborhood.size() => {
+                            transitions[count as usize] = true;
+                        }
+                        Ok(_) => return Err(invalid("a count is larger than the neighborhood")),
+                        Err(_) => {
+                            return Err(invalid(
+                                "counts of neighborhoods above 9 cells are separated by commas",
+                            ))
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            let mut characters = text.chars().peekable();
+            while let Some(digit) = characters.next() {
+                let count = match digit.to_digit(10) {
+                    Some(count) if count <= 8 || neighborhood != Neighborhood::Moore => count,
+                    _ => return Err(invalid("neighbor counts are digits from 0 to 8")),
+                };
+                let is_negated = characters.next_if_eq(&'-').is_some();
+                let mut count_letters = Vec::new();
+                while let Some(letter) = characters.next_if(char::is_ascii_alphabetic) {
+                    if !letters(count).any(|known| known == letter) {
+                        return Err(invalid(&format!("{count}{letter} is not an arrangement")));
+                    }
+                    count_letters.push(letter);
+                }
+                if is_negated && count_letters.is_empty() {
+                    return Err(invalid("a minus must be followed by letters"));
+                }
+
+                // Other neighborhoods only have counts, up to their size
+                if neighborhood != Neighborhood::Moore {
+                    if is_negated || !count_letters.is_empty() {
+                        return Err(invalid("letters only apply to the Moore neighborhood"));
+                    }
+                    if count > neighborhood.size() {
+                        return Err(invalid("a count is larger than the neighborhood"));
+                    }
+                    transitions[count as usize] = true;
+                    continue;
+                }
+
+                for neighborhood in (0..=255_u8).filter(|bits| bits.count_ones() == count) {
+                    let is_listed =
+                        letter(neighborhood).is_some_and(|letter| count_letters.contains(&letter));
+                    if count_letters.is_empty() || is_listed != is_negated {
+                        transitions[neighborhood as usize] = true;
+                    }
+                }
+            }
+            Ok(())
+        };
+        let states = |digits: &str| -> Result<u8, Error> {
+            match digits.parse() {
+                Ok(states) if states >= 2 => Ok(states),
+                _ => Err(invalid("the number of states is from 2 to 255")),
+            }
+        };
+
+        let parts: Vec<&str> = text.split('/').collect();
+        let mut rule = Rule {
+            transitions: [false; 2 * NEIGHBORHOODS],
+            states: 2,
+            neighborhood,
+            larger_than_life: None,
+        };
+        let (birth, survival) = rule.transitions.split_at_mut(SURVIVAL);
+
+        let has_prefixes = parts
+            .iter()
+            .any(|part| part.starts_with(|character: char| character.is_ascii_alphabetic()));
+        if has_prefixes {
+            for part in &parts {
+                let mut characters = part.chars();
+                match characters.next().map(|prefix| prefix.to_ascii_uppercase()) {
+                    Some('B') => conditions(characters.as_str(), birth)?,
+                    Some('S') => conditions(characters.as_str(), survival)?,
+                    Some('C') | Some('G') => rule.states = states(characters.as_str())?,
+                    _ => return Err(invalid("expected parts starting with B, S or C")),
+                }
+            }
+        } else {
+            match parts[..] {
+                [survival_conditions, birth_conditions] => {
+                    conditions(survival_conditions, survival)?;
+                    conditions(birth_conditions, birth)?;
+                }
+                [survival_conditions, birth_conditions, generations] => {
+                    conditions(survival_conditions, survival)?;
+                    conditions(birth_conditions, birth)?;
+                    rule.states = states(generations)?;
+                }
+                _ => return Err(invalid("expected B/S, S/B or S/B/C notation")),
+            }
+        }
+
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(text: &str) -> Rule {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn notations_are_equivalent() {
+        assert_eq!(rule("B3/S23"), Rule::default());
+        assert_eq!(rule("s23/b3"), Rule::default());
+        assert_eq!(rule("23/3"), Rule::default());
+        assert_eq!(rule("Life"), Rule::default());
+        assert_eq!(rule("B2/S/C3"), rule("/2/3"));
+        assert_eq!(rule("B2/S345/G4"), rule("345/2/4"));
+    }
+
+    #[test]
+    fn rules_round_trip() {
+        for text in [
+            "B3/S23",
+            "B36/S23",
+            "B2/S",
+            "/2/3",
+            "345/2/4",
+            "12345/3/48",
+            "B2-a/S12",
+            "B2ea3aijr/S23-a",
+            "B2ka/S2-in3",
+            "B3-ky/S2ce3cnyq",
+            "B3/S2-i34q",
+            "1c2-n/3ck/3",
+            "B2/S34H",
+            "B13/S012V",
+            "23/2/3H",
+            "B3/S23N@201804",
+            "B3,12/S2,3N@ffffff",
+            "B/S0,24N@ffffff",
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R2,C4,M0,S2-3,5,B3,NN",
+        ] {
+            assert_eq!(rule(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        for text in [
+            "B9/S23",
+            "B3/S23/X2",
+            "345/2/1",
+            "345/2/256",
+            "3",
+            "B3/S2b",
+            "B1a/S23",
+            "B0c/S",
+            "B2-/S23",
+            "B5/S23V",
+            "B2a/S34H",
+            "B3/S23N@2018",
+            "B25/S23N@ffffff",
+            "B3,,4/S23N@ffffff",
+        ] {
+            assert!(text.parse::<Rule>().is_err(), "{text} should be invalid");
+        }
+    }
+
+    #[test]
+    fn arrangements_are_told_apart() {
+        let int_rule = rule("B2-a/S12");
+        let (north, north_east, south) = (0b1, 0b10, 0b1_0000);
+
+        assert_eq!(int_rule.next_state(DEAD, north | north_east), DEAD);
+        assert_eq!(int_rule.next_state(DEAD, north | south), ALIVE);
+        // Letters that cover every arrangement of a count are dropped
+        assert_eq!(rule("B2cekain/S1ce").to_string(), "B2/S1");
+        assert_eq!(rule("B3-cekainyqjr/S").to_string(), "B/S");
+    }
+
+    #[test]
+    fn large_neighborhoods_count_past_eight() {
+        let radius_two = rule("B12/S2,3N@ffffff");
+
+        assert_eq!(radius_two.next_state(DEAD, 12), ALIVE);
+        assert_eq!(radius_two.next_state(DEAD, 1), DEAD);
+        assert_eq!(radius_two.next_state(ALIVE, 3), ALIVE);
+        assert_eq!(radius_two.to_string(), "B12/S2,3N@ffffff");
+    }
+
+    #[test]
+    fn dying_cells_decay_through_every_state() {
+        let star_wars = rule("345/2/4");
+
+        assert_eq!(star_wars.next_state(ALIVE, 0b1111), ALIVE);
+        assert_eq!(star_wars.next_state(ALIVE, 0b11), 2);
+        assert_eq!(star_wars.next_state(2, 0b11), 3);
+        assert_eq!(star_wars.next_state(3, 0b11), DEAD);
+        assert_eq!(star_wars.next_state(DEAD, 0b11), ALIVE);
+    }
+}
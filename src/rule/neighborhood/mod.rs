@@ -0,0 +1,129 @@
+use std::fmt;
+use std::fmt::Display;
+
+/// Custom neighborhoods reach at most this far from the cell, so their masks fit in a u64
+pub const MAX_CUSTOM_RADIUS: u8 = 3;
+
+/// The cells whose live neighbors are counted
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Neighborhood {
+    /// The eight surrounding cells
+    #[default]
+    Moore,
+    /// The four orthogonally adjacent cells
+    VonNeumann,
+    /// The six cells around a hexagon, with odd rows shifted half a cell to the right
+    Hexagonal,
+    /// The cells within `radius` whose bit is set in `mask`, row by row from the
+    /// top left and skipping the cell itself, the first cell in the highest bit
+    Custom {
+        /// How far the neighborhood reaches, up to 3 cells
+        radius: u8,
+        /// One bit for each cell within the radius
+        mask: u64,
+    },
+}
+
+impl Neighborhood {
+    /// The number of cells in the neighborhood
+    pub fn size(&self) -> u32 {
+        match self {
+            Neighborhood::Moore => 8,
+            Neighborhood::VonNeumann => 4,
+            Neighborhood::Hexagonal => 6,
+            Neighborhood::Custom { mask, .. } => mask.count_ones(),
+        }
+    }
+
+    /// Odd rows are drawn shifted by half a cell
+    pub fn is_staggered(&self) -> bool {
+        *self == Neighborhood::Hexagonal
+    }
+
+    /// The (column, row) offsets of the neighbors of a cell in the given row
+    pub fn offsets(&self, row: u16) -> Vec<(i16, i16)> {
+        match self {
+            Neighborhood::Moore => Neighborhood::Custom {
+                radius: 1,
+                mask: 0xff,
+            }
+            .offsets(row),
+            Neighborhood::VonNeumann => vec![(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Neighborhood::Hexagonal => {
+                // The rows above and below are shifted the other way
+                let shift = if row.is_multiple_of(2) { -1 } else { 0 };
+                vec![
+                    (-1, 0),
+                    (1, 0),
+                    (shift, -1),
+                    (shift + 1, -1),
+                    (shift, 1),
+                    (shift + 1, 1),
+                ]
+            }
+            Neighborhood::Custom { radius, mask } => {
+                let radius = *radius as i16;
+                let cells = (-radius..=radius)
+                    .flat_map(|row| (-radius..=radius).map(move |column| (column, row)))
+                    .filter(|offset| *offset != (0, 0));
+                let bits = 4 * radius * (radius + 1);
+                cells
+                    .enumerate()
+                    .filter(|(cell, _)| mask & 1 << (bits - 1 - *cell as i16) != 0)
+                    .map(|(_, offset)| offset)
+                    .collect()
+            }
+        }
+    }
+
+    /// Parse the mask of a custom neighborhood, written in hexadecimal. Its length
+    /// gives the radius: 2 digits for radius 1, 6 for radius 2 and 12 for radius 3.
+    pub fn parse_custom(digits: &str) -> Option<Self> {
+        let radius = (1..=MAX_CUSTOM_RADIUS)
+            .find(|radius| (*radius as usize * (*radius as usize + 1)) == digits.len())?;
+        let mask = u64::from_str_radix(digits, 16).ok()?;
+        Some(Neighborhood::Custom { radius, mask })
+    }
+}
+
+// The suffix of the neighborhood in rule strings, as Golly writes them
+impl Display for Neighborhood {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Neighborhood::Moore => Ok(()),
+            Neighborhood::VonNeumann => write!(formatter, "V"),
+            Neighborhood::Hexagonal => write!(formatter, "H"),
+            Neighborhood::Custom { radius, mask } => {
+                let digits = *radius as usize * (*radius as usize + 1);
+                write!(formatter, "N@{mask:0digits$x}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_masks_list_their_cells() {
+        // The cells to the sides, and two cells up and down
+        let mask = 0b0010_0000_0001_1000_0000_0100;
+        let neighborhood = Neighborhood::parse_custom("201804").unwrap();
+
+        assert_eq!(neighborhood, Neighborhood::Custom { radius: 2, mask });
+        assert_eq!(neighborhood.to_string(), "N@201804");
+        assert_eq!(
+            neighborhood.offsets(0),
+            vec![(0, -2), (-1, 0), (1, 0), (0, 2)]
+        );
+        assert_eq!(Neighborhood::parse_custom("2006"), None);
+    }
+
+    #[test]
+    fn hexagonal_neighbors_depend_on_the_row() {
+        assert!(Neighborhood::Hexagonal.offsets(0).contains(&(-1, -1)));
+        assert!(Neighborhood::Hexagonal.offsets(1).contains(&(1, -1)));
+        assert_eq!(Neighborhood::Moore.offsets(0).len(), 8);
+    }
+}
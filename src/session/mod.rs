@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::automaton;
+use crate::error::Error;
+use crate::grid::Anchor;
+use crate::grid::Direction;
+use crate::grid::Grid;
+use crate::grid::Topology;
+use crate::grid::ALIVE;
+use crate::grid::DEAD;
+use crate::grid::DEFAULT_MAX_WORLD_SIZE;
+
+// A session file starts with this line, followed by "key: value" header lines,
+// an empty line and one line of cells per grid row ('O' alive, '.' dead, and
+// 'a', 'b', ... for the decaying states 2, 3, ... of Generations rules).
+// When there are any, an empty line and the ages of the live cells and the
+// cells kept outside of the grid follow, placed from its top left cell. Hidden
+// cells write their state as a number, as some letters of the states past 37
+// are whitespace.
+//
+// game-of-life session
+// version: 5
+// width: 3
+// ...
+//
+// .O.
+// ..O
+//
+// age 1,0 12
+// hidden -4,1 1 30
+const MAGIC: &str = "game-of-life session";
+
+// Each migration upgrades a session from version `index + 1` to `index + 2`.
+// When the layout changes, append a migration instead of editing the parser
+// for old files, so sessions saved by any earlier version can still be resumed.
+type Migration = fn(&mut Fields) -> Result<(), Error>;
+const MIGRATIONS: &[Migration] = &[add_rule, add_anchor, add_topology, add_world_size];
+const VERSION: usize = MIGRATIONS.len() + 1;
+
+// The slowest delay, the game shows speeds from 100 down to 1
+const MAX_DELAY: u8 = 99;
+
+/// A game saved on quit, so it can be resumed where it was
+pub struct Session {
+    /// The grid with its cells, rule and resize settings
+    pub grid: Grid,
+    /// The delay between generations, from 0 to 99
+    pub delay: u8,
+    /// Whether the game was paused
+    pub game_is_paused: bool,
+}
+
+// The raw content of a session file, before it is turned into a `Session`
+struct Fields {
+    header: HashMap<String, String>,
+    rows: Vec<String>,
+    // The ages and the hidden cells after the rows
+    cells: Vec<String>,
+}
+
+impl Fields {
+    fn get(&self, key: &str) -> Result<&str, Error> {
+        self.header
+            .get(key)
+            .map(String::as_str)
+            .ok_or_else(|| invalid_data(format!("missing \"{key}\" in session")))
+    }
+
+    fn parse<T: std::str::FromStr>(&self, key: &str) -> Result<T, Error> {
+        let value = self.get(key)?;
+        value
+            .parse()
+            .map_err(|_| invalid_data(format!("invalid {key} \"{value}\" in session")))
+    }
+
+    fn direction(&self, key: &str) -> Result<Direction, Error> {
+        let value = self.get(key)?;
+        Direction::from_name(value)
+            .ok_or_else(|| invalid_data(format!("invalid {key} \"{value}\" in session")))
+    }
+}
+
+impl Session {
+    /// The text of the session file
+    pub fn serialize(&self) -> String {
+        let grid = &self.grid;
+        let mut text = format!("{MAGIC}\nversion: {VERSION}\n");
+        let header = [
+            ("width", grid.width.to_string()),
+            ("height", grid.height.to_string()),
+            ("generation", grid.generation.to_string()),
+            ("population", grid.population.to_string()),
+            ("delay", self.delay.to_string()),
+            ("paused", self.game_is_paused.to_string()),
+            ("rule", grid.automaton().name()),
+            (
+                "width-append-direction",
+                grid.width_append_direction.name().to_string(),
+            ),
+            (
+                "height-append-direction",
+                grid.height_append_direction.name().to_string(),
+            ),
+            (
+                "width-remove-direction",
+                grid.width_remove_direction.name().to_string(),
+            ),
+            (
+                "height-remove-direction",
+                grid.height_remove_direction.name().to_string(),
+            ),
+            ("anchor", grid.anchor.name().to_string()),
+            ("topology", grid.topology.name().to_string()),
+            (
+                "world-size",
+                format!("{},{}", grid.max_world_size.0, grid.max_world_size.1),
+            ),
+        ];
+        for (key, value) in header {
+            text.push_str(&format!("{key}: {value}\n"));
+        }
+        if let Some(state) = grid.automaton().saved_state() {
+            text.push_str(&format!("automaton-state: {state}\n"));
+        }
+        text.push('\n');
+
+        let mut cells = String::new();
+        for height in 0..grid.height {
+            for width in 0..grid.width {
+                text.push(cell_char(grid[(width, height)]));
+                let age = grid.age((width, height));
+                if age > 0 {
+                    cells.push_str(&format!("age {width},{height} {age}\n"));
+                }
+            }
+            text.push('\n');
+        }
+        for ((width, height), (state, age)) in grid.hidden_cells() {
+            cells.push_str(&format!("hidden {width},{height} {state} {age}\n"));
+        }
+        if !cells.is_empty() {
+            text.push('\n');
+            text.push_str(&cells);
+        }
+
+        text
+    }
+
+    /// Read a session file of this version or of an earlier one
+    pub fn deserialize(text: &str) -> Result<Self, Error> {
+        let mut lines = text.lines();
+        if lines.next() != Some(MAGIC) {
+            return Err(invalid_data("not a session file".to_string()));
+        }
+
+        let mut header = HashMap::new();
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid_data(format!("invalid session line \"{line}\"")))?;
+            header.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        let rows = lines
+            .by_ref()
+            .take_while(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        let cells = lines.map(str::to_string).collect();
+        let mut fields = Fields {
+            header,
+            rows,
+            cells,
+        };
+
+        // Bring sessions saved by older versions up to date. Unknown keys are
+        // ignored, so only versions newer than this one are rejected.
+        let version: usize = fields.parse("version")?;
+        if version == 0 || version > VERSION {
+            return Err(invalid_data(format!(
+                "session version {version} is not supported (latest is {VERSION})"
+            )));
+        }
+        for migration in &MIGRATIONS[version - 1..] {
+            migration(&mut fields)?;
+        }
+
+        let width: u16 = fields.parse("width")?;
+        let height: u16 = fields.parse("height")?;
+        if fields.rows.len() != height as usize {
+            return Err(invalid_data(format!(
+                "session has {} rows instead of {height}",
+                fields.rows.len()
+            )));
+        }
+
+        let mut grid = Grid::new(width, height);
+        let rule = fields.get("rule")?;
+        let mut automaton = automaton::parse(rule)
+            .map_err(|_| invalid_data(format!("invalid rule \"{rule}\" in session")))?;
+        if let Ok(state) = fields.get("automaton-state") {
+            automaton.restore_state(state).map_err(|_| {
+                invalid_data(format!("invalid automaton-state \"{state}\" in session"))
+            })?;
+        }
+        grid.set_automaton(automaton);
+        let states = grid.automaton().states();
+        for (row_index, row) in fields.rows.iter().enumerate() {
+            if row.chars().count() != width as usize {
+                return Err(invalid_data(format!(
+                    "session row {row_index} is not {width} cells wide"
+                )));
+            }
+            for (column_index, cell) in row.chars().enumerate() {
+                let state = cell_state(cell).filter(|state| *state < states);
+                let state = state.ok_or_else(|| {
+                    invalid_data(format!("invalid cell '{cell}' in session row {row_index}"))
+                })?;
+                grid.set_cell((column_index as u16, row_index as u16), state);
+            }
+        }
+
+        for line in &fields.cells {
+            let invalid = || invalid_data(format!("invalid session line \"{line}\""));
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                ["age", index, age] => {
+                    let index: (u16, u16) = parse_index(index).ok_or_else(invalid)?;
+                    if index.0 >= width || index.1 >= height {
+                        return Err(invalid());
+                    }
+                    grid.set_age(index, age.parse().map_err(|_| invalid())?);
+                }
+                ["hidden", index, state, age] => {
+                    let (column, row): (i32, i32) = parse_index(index).ok_or_else(invalid)?;
+                    let is_inside =
+                        (0..width as i32).contains(&column) && (0..height as i32).contains(&row);
+                    let state = state
+                        .parse()
+                        .ok()
+                        .filter(|state| *state != DEAD && *state < states);
+                    match state {
+                        Some(state) if !is_inside => grid
+                            .hide_cell((column, row), (state, age.parse().map_err(|_| invalid())?)),
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        let population: usize = fields.parse("population")?;
+        if population != grid.population {
+            return Err(invalid_data(format!(
+                "session population {population} does not match its {} live cells",
+                grid.population
+            )));
+        }
+
+        grid.generation = fields.parse("generation")?;
+        grid.width_append_direction = fields.direction("width-append-direction")?;
+        grid.height_append_direction = fields.direction("height-append-direction")?;
+        grid.width_remove_direction = fields.direction("width-remove-direction")?;
+        grid.height_remove_direction = fields.direction("height-remove-direction")?;
+        let anchor = fields.get("anchor")?;
+        grid.anchor = Anchor::from_name(anchor)
+            .ok_or_else(|| invalid_data(format!("invalid anchor \"{anchor}\" in session")))?;
+        let topology = fields.get("topology")?;
+        grid.topology = Topology::from_name(topology)
+            .ok_or_else(|| invalid_data(format!("invalid topology \"{topology}\" in session")))?;
+        let world_size = fields.get("world-size")?;
+        grid.max_world_size = parse_index(world_size).ok_or_else(|| {
+            invalid_data(format!("invalid world-size \"{world_size}\" in session"))
+        })?;
+
+        let delay: u8 = fields.parse("delay")?;
+        if delay > MAX_DELAY {
+            return Err(invalid_data(format!(
+                "session delay {delay} is above {MAX_DELAY}"
+            )));
+        }
+
+        Ok(Session {
+            grid,
+            delay,
+            game_is_paused: fields.parse("paused")?,
+        })
+    }
+}
+
+// Version 2 added the rule, sessions saved before always played Conway's Game of Life
+fn add_rule(fields: &mut Fields) -> Result<(), Error> {
+    fields
+        .header
+        .insert("rule".to_string(), "B3/S23".to_string());
+    Ok(())
+}
+
+// Version 3 added the anchor of resizes, which was always the center before
+fn add_anchor(fields: &mut Fields) -> Result<(), Error> {
+    fields
+        .header
+        .insert("anchor".to_string(), Anchor::Center.name().to_string());
+    Ok(())
+}
+
+// Version 4 added the topology, grids were always planes before
+fn add_topology(fields: &mut Fields) -> Result<(), Error> {
+    fields
+        .header
+        .insert("topology".to_string(), Topology::Plane.name().to_string());
+    Ok(())
+}
+
+// Version 5 added the size of the world, the ages and the cells kept outside of
+// the grid, which were lost before
+fn add_world_size(fields: &mut Fields) -> Result<(), Error> {
+    let (width, height) = DEFAULT_MAX_WORLD_SIZE;
+    fields
+        .header
+        .insert("world-size".to_string(), format!("{width},{height}"));
+    Ok(())
+}
+
+/// The session is kept under $XDG_STATE_HOME, falling back to ~/.local/state
+pub fn default_path() -> Option<PathBuf> {
+    let state_home = match env::var_os("XDG_STATE_HOME") {
+        Some(state_home) if !state_home.is_empty() => PathBuf::from(state_home),
+        _ => PathBuf::from(env::var_os("HOME")?)
+            .join(".local")
+            .join("state"),
+    };
+    Some(state_home.join("game-of-life").join("session"))
+}
+
+/// Save a session, creating its directory
+pub fn save(path: &Path, session: &Session) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::write(path, session.serialize())?)
+}
+
+/// Read a session file
+pub fn load(path: &Path) -> Result<Session, Error> {
+    Session::deserialize(&fs::read_to_string(path)?)
+}
+
+fn cell_char(state: u8) -> char {
+    match state {
+        DEAD => '.',
+        ALIVE => 'O',
+        state => char::from_u32('a' as u32 + state as u32 - 2).unwrap_or('?'),
+    }
+}
+
+fn cell_state(cell: char) -> Option<u8> {
+    match cell {
+        '.' => Some(DEAD),
+        'O' => Some(ALIVE),
+        _ => (cell as u32)
+            .checked_sub('a' as u32 - 2)
+            .filter(|state| *state >= 2)
+            .and_then(|state| u8::try_from(state).ok()),
+    }
+}
+
+// A place or a size written as COLUMN,ROW
+fn parse_index<T: std::str::FromStr>(text: &str) -> Option<(T, T)> {
+    let (column, row) = text.split_once(',')?;
+    Some((column.parse().ok()?, row.parse().ok()?))
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::InvalidSession(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_round_trip() {
+        let mut grid = Grid::new(7, 5);
+        for cell_index in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            grid.cycle_cell(cell_index);
+        }
+        grid.next_generation();
+        grid.resize(8, 4);
+        grid.anchor = Anchor::CenterOfMass;
+        grid.topology = Topology::Torus;
+        let session = Session {
+            grid,
+            delay: 12,
+            game_is_paused: false,
+        };
+
+        let restored = Session::deserialize(&session.serialize()).unwrap();
+
+        assert_eq!(restored.serialize(), session.serialize());
+        assert_eq!(restored.grid.generation, 1);
+        assert_eq!(restored.grid.population, session.grid.population);
+        assert!(
+            restored.grid.width_append_direction == session.grid.width_append_direction
+                && restored.grid.height_remove_direction == session.grid.height_remove_direction,
+            "The resize directions are not restored!"
+        );
+        assert_eq!(restored.grid.anchor, Anchor::CenterOfMass);
+        assert_eq!(restored.grid.topology, Topology::Torus);
+        assert_eq!(restored.delay, 12);
+        assert!(!restored.game_is_paused);
+    }
+
+    #[test]
+    fn generations_states_are_saved() {
+        let mut grid = Grid::new(3, 1);
+        grid.set_automaton(automaton::parse("345/2/4").unwrap());
+        grid.set_cell((1, 0), 2);
+        grid.set_cell((2, 0), 3);
+        let session = Session {
+            grid,
+            delay: 50,
+            game_is_paused: true,
+        };
+
+        let text = session.serialize();
+        assert!(text.contains("rule: 345/2/4\n") && text.ends_with("\n.ab\n"));
+        let restored = Session::deserialize(&text).unwrap();
+        assert_eq!(restored.grid[(2, 0)], 3);
+        assert_eq!(restored.grid.automaton().name(), "345/2/4");
+    }
+
+    #[test]
+    fn hidden_cells_come_back_after_resuming() {
+        let mut grid = Grid::new(10, 6);
+        grid.max_world_size = (30, 20);
+        grid.set_automaton(automaton::parse("Ant:RL").unwrap());
+        for _ in 0..3 {
+            grid.next_generation();
+        }
+        for cell_index in [(0, 0), (9, 5), (8, 1)] {
+            grid.set_cell(cell_index, ALIVE);
+            grid.set_age(cell_index, 7);
+        }
+        grid.resize(6, 4);
+        let session = Session {
+            grid,
+            delay: 50,
+            game_is_paused: true,
+        };
+
+        let text = session.serialize();
+        let mut restored = Session::deserialize(&text).unwrap();
+
+        assert_eq!(restored.serialize(), text);
+        assert_eq!(restored.grid.max_world_size, (30, 20));
+        assert_eq!(restored.grid.hidden_population(), 3);
+        restored.grid.resize(10, 6);
+        for cell_index in [(0, 0), (9, 5), (8, 1)] {
+            assert_eq!(restored.grid[cell_index], ALIVE);
+            assert_eq!(restored.grid.age(cell_index), 7);
+        }
+        // The ant goes on where it was, on the cells it colored
+        let mut grid = session.grid;
+        grid.resize(10, 6);
+        grid.next_generation();
+        restored.grid.next_generation();
+        assert!(restored.grid.automaton().saved_state().is_some());
+        let restored = Session {
+            grid: restored.grid,
+            ..session
+        };
+        let session = Session { grid, ..session };
+        assert_eq!(restored.serialize(), session.serialize());
+    }
+
+    #[test]
+    fn hidden_states_past_the_letters_are_saved() {
+        // The letters of states 38 and 65 would be U+0085 and U+00A0
+        let mut grid = Grid::new(10, 10);
+        grid.set_automaton(automaton::parse("3/2/70").unwrap());
+        grid.set_cell((9, 9), 38);
+        grid.set_cell((8, 9), 65);
+        grid.resize(5, 5);
+        let session = Session {
+            grid,
+            delay: 50,
+            game_is_paused: true,
+        };
+
+        let mut restored = Session::deserialize(&session.serialize()).unwrap();
+
+        restored.grid.resize(10, 10);
+        assert_eq!([restored.grid[(9, 9)], restored.grid[(8, 9)]], [38, 65]);
+    }
+
+    #[test]
+    fn first_version_sessions_play_life() {
+        let text = "game-of-life session\nversion: 1\nwidth: 2\nheight: 1\ngeneration: 3\n\
+            population: 1\ndelay: 50\npaused: true\nwidth-append-direction: right\n\
+            height-append-direction: bottom\nwidth-remove-direction: right\n\
+            height-remove-direction: bottom\n\nO.\n";
+
+        let session = Session::deserialize(text).unwrap();
+
+        assert_eq!(session.grid.automaton().name(), "B3/S23");
+        assert_eq!(session.grid.population, 1);
+    }
+
+    #[test]
+    fn newer_sessions_are_rejected() {
+        let session = Session {
+            grid: Grid::new(2, 2),
+            delay: 50,
+            game_is_paused: true,
+        };
+        let text = session.serialize().replace(
+            &format!("version: {VERSION}"),
+            &format!("version: {}", VERSION + 1),
+        );
+
+        assert!(Session::deserialize(&text).is_err());
+    }
+
+    #[test]
+    fn delays_past_the_slowest_are_rejected() {
+        let session = Session {
+            grid: Grid::new(2, 2),
+            delay: MAX_DELAY,
+            game_is_paused: true,
+        };
+        let text = session.serialize();
+
+        assert!(Session::deserialize(&text).is_ok());
+        let text = text.replace("delay: 99", "delay: 200");
+        assert!(matches!(
+            Session::deserialize(&text),
+            Err(Error::InvalidSession(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let session = Session {
+            grid: Grid::new(2, 2),
+            delay: 50,
+            game_is_paused: true,
+        };
+        let text = session
+            .serialize()
+            .replacen("width:", "comment: saved by hand\nwidth:", 1);
+
+        assert!(Session::deserialize(&text).is_ok());
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::error::Error;
+use crate::grid::Grid;
+
+// The generations per second are measured over this many recent generations
+const RATE_WINDOW: usize = 16;
+
+// Blocks used to draw the population chart, from empty to full
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// What happened in a generation
+pub struct Record {
+    /// The number of the generation
+    pub generation: usize,
+    /// The number of live cells
+    pub population: usize,
+    /// The number of cells that were born into it
+    pub births: usize,
+    /// The number of cells that died on the way to it
+    pub deaths: usize,
+}
+
+/// The population of every generation since the game started, with running totals
+/// so the summary doesn't have to walk the whole history on every generation.
+pub struct Statistics {
+    /// A record of every generation, the first one first
+    pub history: Vec<Record>,
+    min_population: usize,
+    max_population: usize,
+    population_sum: u128,
+    generation_times: VecDeque<Instant>,
+}
+
+impl Statistics {
+    /// Statistics that start from the current generation of the grid
+    pub fn new(grid: &Grid) -> Self {
+        let mut statistics = Statistics {
+            history: Vec::new(),
+            min_population: usize::MAX,
+            max_population: 0,
+            population_sum: 0,
+            generation_times: VecDeque::new(),
+        };
+        statistics.record(grid);
+        statistics
+    }
+
+    /// Record the current generation of the grid
+    pub fn record(&mut self, grid: &Grid) {
+        self.history.push(Record {
+            generation: grid.generation,
+            population: grid.population,
+            births: grid.births,
+            deaths: grid.deaths,
+        });
+        self.min_population = self.min_population.min(grid.population);
+        self.max_population = self.max_population.max(grid.population);
+        self.population_sum += grid.population as u128;
+
+        if self.generation_times.len() == RATE_WINDOW {
+            self.generation_times.pop_front();
+        }
+        self.generation_times.push_back(Instant::now());
+    }
+
+    /// The mean population of the generations of the history
+    pub fn mean_population(&self) -> f64 {
+        self.population_sum as f64 / self.history.len() as f64
+    }
+
+    /// How fast the last generations were recorded
+    pub fn generations_per_second(&self) -> f64 {
+        match (self.generation_times.front(), self.generation_times.back()) {
+            (Some(first), Some(last)) if last > first => {
+                (self.generation_times.len() - 1) as f64 / (*last - *first).as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Draw the population of the most recent generations, one per column,
+    /// as a bar chart scaled between the smallest and largest of them.
+    pub fn chart(&self, width: usize, height: usize) -> Vec<String> {
+        let recent = &self.history[self.history.len().saturating_sub(width)..];
+        let min = recent
+            .iter()
+            .map(|record| record.population)
+            .min()
+            .unwrap_or(0);
+        let max = recent
+            .iter()
+            .map(|record| record.population)
+            .max()
+            .unwrap_or(0);
+
+        // Bar heights in eighths of a row, a flat history is drawn at half height
+        let eighths: Vec<usize> = recent
+            .iter()
+            .map(|record| match max - min {
+                0 => height * 4,
+                range => 1 + (record.population - min) * (height * 8 - 1) / range,
+            })
+            .collect();
+
+        (0..height)
+            .map(|row| {
+                let row_bottom = (height - 1 - row) * 8;
+                eighths
+                    .iter()
+                    .map(|bar| BLOCKS[bar.saturating_sub(row_bottom).min(8)])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The lines of the side panel, each at most `width` characters wide
+    pub fn panel_lines(&self, grid: &Grid, width: usize, chart_height: usize) -> Vec<String> {
+        let mut lines = vec!["Population history".to_string()];
+        lines.extend(self.chart(width, chart_height));
+
+        let last = self.history.last();
+        let (bounding_box, density) = match grid.bounding_box() {
+            Some((left, top, right, bottom)) => {
+                let (box_width, box_height) = (right - left + 1, bottom - top + 1);
+                let area = box_width as f64 * box_height as f64;
+                (
+                    format!("{box_width}x{box_height}"),
+                    format!("{:.1}%", 100.0 * grid.population as f64 / area),
+                )
+            }
+            None => ("-".to_string(), "-".to_string()),
+        };
+        lines.extend([
+            format!("Min: {}", self.min_population),
+            format!("Max: {}", self.max_population),
+            format!("Mean: {:.1}", self.mean_population()),
+            format!("Births: {}", last.map_or(0, |record| record.births)),
+            format!("Deaths: {}", last.map_or(0, |record| record.deaths)),
+            format!("Bounding box: {bounding_box}"),
+            format!("Density: {density}"),
+            format!("Speed: {:.1} gen/s", self.generations_per_second()),
+        ]);
+
+        lines
+            .into_iter()
+            .map(|line| line.chars().take(width).collect())
+            .collect()
+    }
+
+    /// The history as CSV, with a header line
+    pub fn to_csv(&self) -> String {
+        let mut csv = "generation,population,births,deaths\n".to_string();
+        for record in &self.history {
+            // Writing into a String never fails
+            let _ = writeln!(
+                csv,
+                "{},{},{},{}",
+                record.generation, record.population, record.births, record.deaths
+            );
+        }
+        csv
+    }
+
+    /// Save the history as a CSV file
+    pub fn save_csv(&self, path: &Path) -> Result<(), Error> {
+        Ok(fs::write(path, self.to_csv())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider_statistics(generations: usize) -> (Grid, Statistics) {
+        let mut grid = Grid::new(20, 20);
+        for cell_index in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            grid.cycle_cell(cell_index);
+        }
+        let mut statistics = Statistics::new(&grid);
+        for _ in 0..generations {
+            grid.next_generation();
+            statistics.record(&grid);
+        }
+        (grid, statistics)
+    }
+
+    #[test]
+    fn history_is_saved_as_csv() {
+        let (_, statistics) = glider_statistics(2);
+
+        assert_eq!(
+            statistics.to_csv(),
+            "generation,population,births,deaths\n0,5,0,0\n1,5,2,2\n2,5,2,2\n"
+        );
+        assert_eq!(statistics.mean_population(), 5.0);
+    }
+
+    #[test]
+    fn chart_scales_to_the_recent_range() {
+        let mut grid = Grid::new(10, 10);
+        let mut statistics = Statistics::new(&grid);
+        for cell_index in [(0, 0), (1, 1)] {
+            grid.cycle_cell(cell_index);
+            statistics.record(&grid);
+        }
+
+        assert_eq!(statistics.chart(3, 2), vec!["  █", "▁██"]);
+        // Only the two most recent generations fit
+        assert_eq!(statistics.chart(2, 1), vec!["▁█"]);
+    }
+}